@@ -0,0 +1,273 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use jsonschema::Validator;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use tokio::{fs, sync::OnceCell};
+
+use crate::{jsonpath::JsonPath, Assertion, ResponseData};
+
+/// A parsed `<subject> <op> <value>` expression. The value is JSON if it parses as such, and a
+/// string otherwise, so `version == HTTP/2.0` needs no quotes. It is saved as written.
+#[derive(Debug, Clone, Serialize)]
+#[serde(into = "String")]
+pub struct Check {
+    source: String,
+    subject: Subject,
+    op: Op,
+    expected: Value,
+}
+
+/// The compiled schema of an [`Assertion::Schema`], read by its first check. Clones share it.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaCache {
+    inner: Arc<OnceCell<Validator>>,
+}
+
+#[derive(Debug, Clone)]
+enum Subject {
+    Status,
+    Version,
+    /// The nodes selected in the body, or their lengths.
+    Body {
+        path: JsonPath,
+        length: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+const OPS: [(&str, Op); 6] = [
+    ("==", Op::Eq),
+    ("!=", Op::Ne),
+    (">=", Op::Ge),
+    ("<=", Op::Le),
+    (">", Op::Gt),
+    ("<", Op::Lt),
+];
+
+impl Assertion {
+    /// What `res` breaks of this assertion, if anything.
+    pub(crate) async fn violations(&self, res: &ResponseData) -> Result<Vec<String>> {
+        match self {
+            Assertion::Expr(check) => Ok(check.check(res).into_iter().collect()),
+            Assertion::Schema {
+                schema,
+                base,
+                validator,
+            } => {
+                let path = match base {
+                    Some(base) => base.join(schema),
+                    None => schema.clone(),
+                };
+                Ok(check_schema(validator.get(&path).await?, schema, res))
+            }
+        }
+    }
+
+    /// Whether this assertion looks at the body, which streamed responses only keep windows of.
+    pub(crate) fn reads_body(&self) -> bool {
+        match self {
+            Assertion::Expr(check) => matches!(check.subject, Subject::Body { .. }),
+            Assertion::Schema { .. } => true,
+        }
+    }
+}
+
+/// Expressions are parsed as the config is read, so that a broken one fails the whole config.
+impl<'de> Deserialize<'de> for Assertion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Expr(String),
+            Schema { schema: PathBuf },
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Expr(expr) => expr
+                .parse()
+                .map(Assertion::Expr)
+                .map_err(|e| de::Error::custom(format!("{:#}", e))),
+            Raw::Schema { schema } => Ok(Assertion::Schema {
+                schema,
+                base: None,
+                validator: SchemaCache::default(),
+            }),
+        }
+    }
+}
+
+impl From<Check> for String {
+    fn from(check: Check) -> Self {
+        check.source
+    }
+}
+
+impl SchemaCache {
+    /// The validator for the schema at `path`, compiled on first use.
+    async fn get(&self, path: &Path) -> Result<&Validator> {
+        self.inner
+            .get_or_try_init(|| async {
+                let schema = fs::read_to_string(path)
+                    .await
+                    .with_context(|| format!("failed to read schema {}", path.display()))?;
+                let schema: Value = serde_json::from_str(&schema)
+                    .with_context(|| format!("schema {} is not JSON", path.display()))?;
+                jsonschema::validator_for(&schema)
+                    .map_err(|e| anyhow!("schema {} is invalid: {}", path.display(), e))
+            })
+            .await
+    }
+}
+
+impl Check {
+    fn check(&self, res: &ResponseData) -> Option<String> {
+        let actual = match &self.subject {
+            Subject::Status => vec![Value::from(res.status.as_u16())],
+            Subject::Version => vec![Value::from(format!("{:?}", res.version))],
+            Subject::Body { path, length } => {
+                let body: Value = match serde_json::from_str(&res.body) {
+                    Ok(body) => body,
+                    Err(_) => return Some(format!("{}: body is not JSON", self)),
+                };
+                let nodes = path.select(&body);
+                if nodes.is_empty() {
+                    return Some(format!("{}: nothing matched", self));
+                }
+                let values = nodes.into_iter().map(|node| match (length, node) {
+                    (false, node) => Some(node.clone()),
+                    (true, Value::Array(items)) => Some(items.len().into()),
+                    (true, Value::Object(map)) => Some(map.len().into()),
+                    (true, Value::String(s)) => Some(s.chars().count().into()),
+                    (true, _) => None,
+                });
+                match values.collect::<Option<Vec<_>>>() {
+                    Some(values) => values,
+                    None => return Some(format!("{}: matched a value without a length", self)),
+                }
+            }
+        };
+        actual
+            .into_iter()
+            .find(|value| !self.op.holds(value, &self.expected))
+            .map(|value| format!("{}: got {}", self, value))
+    }
+}
+
+impl Op {
+    fn holds(self, actual: &Value, expected: &Value) -> bool {
+        let ordering = match (actual, expected) {
+            (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            _ if actual == expected => Some(Ordering::Equal),
+            _ => None,
+        };
+        match self {
+            Op::Eq => ordering == Some(Ordering::Equal),
+            Op::Ne => ordering != Some(Ordering::Equal),
+            Op::Gt => ordering == Some(Ordering::Greater),
+            Op::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            Op::Lt => ordering == Some(Ordering::Less),
+            Op::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        OPS.iter().find(|(_, op)| *op == self).unwrap().0
+    }
+}
+
+impl FromStr for Check {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // The first operator from the left, preferring the two-character ones at a position.
+        let (idx, token, op) = s
+            .char_indices()
+            .find_map(|(idx, _)| {
+                OPS.iter()
+                    .find(|(token, _)| s[idx..].starts_with(token))
+                    .map(|(token, op)| (idx, *token, *op))
+            })
+            .ok_or_else(|| anyhow!("assertion `{}` has no comparison operator", s))?;
+        let (subject, value) = (s[..idx].trim(), s[idx + token.len()..].trim());
+        let subject = match subject {
+            "status" => Subject::Status,
+            "version" => Subject::Version,
+            path if path.starts_with('$') => {
+                let (path, length) = match path.strip_suffix(".length") {
+                    Some(path) => (path, true),
+                    None => (path, false),
+                };
+                Subject::Body {
+                    path: path.parse()?,
+                    length,
+                }
+            }
+            _ => bail!(
+                "assertion `{}` must be about status, version or a JSONPath",
+                s
+            ),
+        };
+        if value.is_empty() {
+            bail!("assertion `{}` has no value to compare with", s);
+        }
+        let expected = serde_json::from_str(value).unwrap_or_else(|_| Value::from(value));
+        Ok(Self {
+            source: s.to_string(),
+            subject,
+            op,
+            expected,
+        })
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.subject {
+            Subject::Status => write!(f, "status")?,
+            Subject::Version => write!(f, "version")?,
+            Subject::Body { path, length } => {
+                write!(f, "{}", path)?;
+                if *length {
+                    write!(f, ".length")?;
+                }
+            }
+        }
+        write!(f, " {} {}", self.op.as_str(), self.expected)
+    }
+}
+
+fn check_schema(validator: &Validator, path: &Path, res: &ResponseData) -> Vec<String> {
+    let body: Value = match serde_json::from_str(&res.body) {
+        Ok(body) => body,
+        Err(_) => return vec![format!("body is not JSON for schema {}", path.display())],
+    };
+    validator
+        .iter_errors(&body)
+        .map(|e| match e.instance_path().to_string() {
+            at if at.is_empty() => format!("body doesn't match schema {}: {}", path.display(), e),
+            at => format!(
+                "body at {} doesn't match schema {}: {}",
+                at,
+                path.display(),
+                e
+            ),
+        })
+        .collect()
+}
@@ -0,0 +1,157 @@
+use std::{collections::BTreeMap, path::Path, time::SystemTime};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use tokio::fs;
+
+use crate::DiffAlgorithm;
+
+/// Differences accepted for now, by case, as written by `xdiff accept`. A case whose every
+/// difference is accepted counts as equal.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Baseline {
+    #[serde(default)]
+    pub cases: BTreeMap<String, Vec<AcceptedDiff>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AcceptedDiff {
+    /// The removed and added lines of a hunk without their context, so that it still matches
+    /// when it moves.
+    pub hunk: String,
+    /// The day, as `YYYY-MM-DD`, from which the difference is no longer accepted.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub expires: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub note: Option<String>,
+}
+
+/// How much of a case's diff the baseline accepts.
+#[derive(Debug, Clone, Default)]
+pub struct Review {
+    pub accepted: usize,
+    pub total: usize,
+    /// What was accepted or has expired, for the report.
+    pub notes: Vec<String>,
+}
+
+impl Baseline {
+    /// Load a baseline, or start an empty one if `path` doesn't exist.
+    pub async fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read baseline {}", path.display()))?;
+        serde_yaml::from_str(&content)
+            .with_context(|| format!("failed to parse baseline {}", path.display()))
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_yaml::to_string(self)?).await?;
+        Ok(())
+    }
+
+    /// Accept the current hunks of a case, in place of what was accepted for it before. Hunks
+    /// that were already accepted keep their expiry and note.
+    pub fn accept(
+        &mut self,
+        case: &str,
+        hunks: Vec<String>,
+        expires: Option<&str>,
+        note: Option<&str>,
+    ) {
+        let previous = self.cases.remove(case).unwrap_or_default();
+        let accepted: Vec<_> = hunks
+            .into_iter()
+            .map(|hunk| match previous.iter().find(|a| a.hunk == hunk) {
+                Some(accepted) => accepted.clone(),
+                None => AcceptedDiff {
+                    hunk,
+                    expires: expires.map(str::to_string),
+                    note: note.map(str::to_string),
+                },
+            })
+            .collect();
+        if !accepted.is_empty() {
+            self.cases.insert(case.to_string(), accepted);
+        }
+    }
+
+    /// Check the hunks of a case against what is accepted for it at `now`.
+    pub fn review(&self, case: &str, hunks: &[String], now: SystemTime) -> Result<Review> {
+        let mut review = Review {
+            total: hunks.len(),
+            ..Default::default()
+        };
+        let accepted = self.cases.get(case).map(Vec::as_slice).unwrap_or_default();
+        for hunk in hunks {
+            let entry = match accepted.iter().find(|a| &a.hunk == hunk) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let note = entry.note.as_deref().unwrap_or("no note");
+            match &entry.expires {
+                Some(day) if now >= parse_day(day)? => review
+                    .notes
+                    .push(format!("accepted difference expired on {}: {}", day, note)),
+                Some(day) => {
+                    review.accepted += 1;
+                    review
+                        .notes
+                        .push(format!("accepted difference until {}: {}", day, note));
+                }
+                None => {
+                    review.accepted += 1;
+                    review.notes.push(format!("accepted difference: {}", note));
+                }
+            }
+        }
+        if review.accepted > 0 && !review.is_accepted() {
+            review.notes.push(format!(
+                "{} of {} differences are accepted in the baseline",
+                review.accepted, review.total
+            ));
+        }
+        Ok(review)
+    }
+}
+
+impl Review {
+    pub fn is_accepted(&self) -> bool {
+        self.accepted == self.total
+    }
+}
+
+/// The changed lines of each hunk of the line diff, as `-line` and `+line`.
+pub fn hunks(text1: &str, text2: &str, algorithm: DiffAlgorithm) -> Vec<String> {
+    let diff = TextDiff::configure()
+        .algorithm(algorithm.into())
+        .diff_lines(text1, text2);
+    diff.grouped_ops(0)
+        .iter()
+        .map(|group| {
+            group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .filter_map(|change| {
+                    let sign = match change.tag() {
+                        ChangeTag::Delete => "-",
+                        ChangeTag::Insert => "+",
+                        ChangeTag::Equal => return None,
+                    };
+                    Some(format!("{}{}", sign, change.value().trim_end_matches('\n')))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect()
+}
+
+/// Parse a `YYYY-MM-DD` day as its midnight UTC.
+pub fn parse_day(day: &str) -> Result<SystemTime> {
+    humantime::parse_rfc3339(&format!("{}T00:00:00Z", day))
+        .with_context(|| format!("{} is not a YYYY-MM-DD date", day))
+}
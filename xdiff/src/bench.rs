@@ -0,0 +1,257 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashSet},
+    fmt::Write,
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+
+use anyhow::Result;
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
+
+use crate::{DiffArgs, DiffCase, ResponseData};
+
+/// Send both requests of a case `requests` times, `concurrency` pairs at a time, and compare
+/// how the two sides perform.
+pub struct Bench<'a> {
+    pub case: &'a DiffCase,
+    pub requests: usize,
+    pub concurrency: usize,
+    pub args: &'a DiffArgs,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub name: String,
+    pub req1: SideStats,
+    pub req2: SideStats,
+    /// Pairs where both requests succeeded but the filtered responses differ, or couldn't be
+    /// filtered, e.g. by a transform failing on one of them.
+    pub differing: usize,
+    /// Pairs where both requests succeeded.
+    pub compared: usize,
+}
+
+/// What one side's requests did. Failed requests and 5xx responses count as errors and are
+/// left out of the latencies.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SideStats {
+    pub requests: usize,
+    pub errors: usize,
+    pub statuses: BTreeMap<u16, usize>,
+    #[serde(rename = "p50_ms", with = "millis")]
+    pub p50: Duration,
+    #[serde(rename = "p95_ms", with = "millis")]
+    pub p95: Duration,
+    #[serde(rename = "p99_ms", with = "millis")]
+    pub p99: Duration,
+    #[serde(rename = "max_ms", with = "millis")]
+    pub max: Duration,
+    /// Distinct filtered responses seen, 1 for a perfectly stable endpoint.
+    pub distinct: usize,
+}
+
+type Sample = (Duration, Result<ResponseData>);
+
+impl Bench<'_> {
+    pub async fn run(&self) -> Result<BenchReport> {
+        let profile = &self.case.profile;
+        // One client per side, so that connections are set up once and not measured.
+        let (client1, client2) = (profile.req1.client()?, profile.req2.client()?);
+        let samples: Vec<(Sample, Sample)> = stream::iter(0..self.requests)
+            .map(|_| async {
+                tokio::join!(
+                    profile.req1.send_timed(self.args, &client1),
+                    profile.req2.send_timed(self.args, &client2)
+                )
+            })
+            .buffer_unordered(self.concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut report = BenchReport {
+            name: self.case.name.clone(),
+            req1: SideStats::default(),
+            req2: SideStats::default(),
+            differing: 0,
+            compared: 0,
+        };
+        let (mut latencies1, mut latencies2) = (Vec::new(), Vec::new());
+        let (mut seen1, mut seen2) = (HashSet::new(), HashSet::new());
+        for ((time1, res1), (time2, res2)) in samples {
+            let ok1 = report.req1.record(time1, &res1, &mut latencies1);
+            let ok2 = report.req2.record(time2, &res2, &mut latencies2);
+            if let (Ok(res1), Ok(res2)) = (&res1, &res2) {
+                report.compared += 1;
+                let Ok((text1, text2)) = profile.filter_text(res1, res2) else {
+                    report.differing += 1;
+                    continue;
+                };
+                if ok1 {
+                    seen1.insert(digest(&text1));
+                }
+                if ok2 {
+                    seen2.insert(digest(&text2));
+                }
+                if text1 != text2 {
+                    report.differing += 1;
+                }
+            }
+        }
+        report.req1.finish(latencies1, seen1.len());
+        report.req2.finish(latencies2, seen2.len());
+        Ok(report)
+    }
+}
+
+fn digest(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl SideStats {
+    /// Count one request, returning whether it succeeded.
+    fn record(
+        &mut self,
+        time: Duration,
+        res: &Result<ResponseData>,
+        latencies: &mut Vec<Duration>,
+    ) -> bool {
+        self.requests += 1;
+        let ok = match res {
+            Ok(res) => {
+                *self.statuses.entry(res.status.as_u16()).or_default() += 1;
+                !res.status.is_server_error()
+            }
+            Err(_) => false,
+        };
+        if ok {
+            latencies.push(time);
+        } else {
+            self.errors += 1;
+        }
+        ok
+    }
+
+    fn finish(&mut self, mut latencies: Vec<Duration>, distinct: usize) {
+        latencies.sort();
+        self.p50 = percentile(&latencies, 50);
+        self.p95 = percentile(&latencies, 95);
+        self.p99 = percentile(&latencies, 99);
+        self.max = latencies.last().copied().unwrap_or_default();
+        self.distinct = distinct;
+    }
+
+    fn error_rate(&self) -> f64 {
+        ratio(self.errors, self.requests)
+    }
+}
+
+/// The nearest-rank percentile of sorted values.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+fn ratio(n: usize, total: usize) -> f64 {
+    match total {
+        0 => 0.0,
+        _ => n as f64 * 100.0 / total as f64,
+    }
+}
+
+impl BenchReport {
+    /// A table of both sides, with the change from `req1` to `req2` for the latencies.
+    pub fn render(&self) -> Result<String> {
+        let mut out = String::new();
+        writeln!(out, "{}", console::style(&self.name).bold())?;
+        writeln!(
+            out,
+            "{:<10} {:>16} {:>16} {:>20}",
+            "", "req1", "req2", "change"
+        )?;
+        let (a, b) = (&self.req1, &self.req2);
+        writeln!(
+            out,
+            "{:<10} {:>16} {:>16}",
+            "requests", a.requests, b.requests
+        )?;
+        writeln!(
+            out,
+            "{:<10} {:>16} {:>16}",
+            "errors",
+            format!("{} ({:.1}%)", a.errors, a.error_rate()),
+            format!("{} ({:.1}%)", b.errors, b.error_rate())
+        )?;
+        for (label, t1, t2) in [
+            ("p50", a.p50, b.p50),
+            ("p95", a.p95, b.p95),
+            ("p99", a.p99, b.p99),
+            ("max", a.max, b.max),
+        ] {
+            writeln!(
+                out,
+                "{:<10} {:>16} {:>16} {:>20}",
+                label,
+                format_ms(t1),
+                format_ms(t2),
+                change(t1, t2)
+            )?;
+        }
+        writeln!(
+            out,
+            "{:<10} {:>16} {:>16}",
+            "distinct", a.distinct, b.distinct
+        )?;
+        writeln!(
+            out,
+            "{:<10} {:>16} {:>16}",
+            "statuses",
+            format_statuses(&a.statuses),
+            format_statuses(&b.statuses)
+        )?;
+        writeln!(
+            out,
+            "differing pairs: {} of {} ({:.1}%)",
+            self.differing,
+            self.compared,
+            ratio(self.differing, self.compared)
+        )?;
+        Ok(out)
+    }
+}
+
+fn format_ms(d: Duration) -> String {
+    format!("{:.1}ms", d.as_secs_f64() * 1000.0)
+}
+
+fn format_statuses(statuses: &BTreeMap<u16, usize>) -> String {
+    statuses
+        .iter()
+        .map(|(status, n)| format!("{}:{}", status, n))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn change(t1: Duration, t2: Duration) -> String {
+    let (t1, t2) = (t1.as_secs_f64() * 1000.0, t2.as_secs_f64() * 1000.0);
+    if t1 == 0.0 {
+        return String::new();
+    }
+    format!("{:+.1}ms ({:+.1}%)", t2 - t1, (t2 - t1) * 100.0 / t1)
+}
+
+/// Durations as fractional milliseconds.
+mod millis {
+    use std::time::Duration;
+
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(d: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(d.as_secs_f64() * 1000.0)
+    }
+}
@@ -0,0 +1,190 @@
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use clap::{Parser, Subcommand};
+
+use crate::{mock::Side, DiffArgs, OutputFormat, Override};
+
+/// Diff two http requests and compare the difference of the responses.
+#[derive(Parser, Debug, Clone)]
+#[clap(version, author, about, long_about = None)]
+pub struct Args {
+    #[clap(subcommand)]
+    pub action: Action,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Action {
+    /// Diff two API responses based on the given profile.
+    Run(RunArgs),
+    /// Accept the current differences of profiles in a baseline file, so that `run --baseline`
+    /// only reports new ones.
+    Accept(AcceptArgs),
+    /// Re-run a profile periodically and report only when the result changes.
+    Watch(WatchArgs),
+    /// Browse the results of all profiles interactively and tune their skip rules.
+    Tui(TuiArgs),
+    /// Send one side of a profile and print the response.
+    Req(ReqArgs),
+    /// Send both requests of a profile many times and compare their latencies and errors.
+    Bench(BenchArgs),
+    /// Serve the responses recorded for one side as a fake upstream.
+    Mock(MockArgs),
+    /// Check a config file and report every problem found, with its location.
+    Validate(ValidateArgs),
+    /// Print a JSON Schema of the config file for editor validation.
+    Schema,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct RunArgs {
+    /// Profile name. Runs every profile when omitted.
+    #[clap(short, long, value_parser)]
+    pub profile: Option<String>,
+    /// Configuration file to use.
+    #[clap(short, long, value_parser, default_value = "xdiff.yml")]
+    pub config: PathBuf,
+    /// Store the raw responses in this directory.
+    #[clap(long, value_parser, conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+    /// Diff the responses stored in this directory instead of sending the requests.
+    #[clap(long, value_parser)]
+    pub replay: Option<PathBuf>,
+    /// Output format.
+    #[clap(short, long, value_enum, default_value_t = OutputFormat::Terminal)]
+    pub format: OutputFormat,
+    /// Also write a self-contained HTML report to this file.
+    #[clap(long, value_parser)]
+    pub html: Option<PathBuf>,
+    /// Count cases whose differences are all accepted in this baseline file as equal.
+    #[clap(short, long, value_parser)]
+    pub baseline: Option<PathBuf>,
+    #[clap(flatten)]
+    pub diff: DiffArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct AcceptArgs {
+    /// Profile name. Accepts the differences of every profile when omitted.
+    #[clap(short, long, value_parser)]
+    pub profile: Option<String>,
+    /// Configuration file to use.
+    #[clap(short, long, value_parser, default_value = "xdiff.yml")]
+    pub config: PathBuf,
+    /// Baseline file to update.
+    #[clap(short, long, value_parser, default_value = "xdiff-baseline.yml")]
+    pub baseline: PathBuf,
+    /// The day, as `YYYY-MM-DD`, from which newly accepted differences fail again.
+    #[clap(long, value_parser = parse_day)]
+    pub expires: Option<String>,
+    /// Why newly accepted differences are expected.
+    #[clap(short, long, value_parser)]
+    pub note: Option<String>,
+    #[clap(flatten)]
+    pub diff: DiffArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct WatchArgs {
+    /// Profile name.
+    #[clap(short, long, value_parser)]
+    pub profile: String,
+    /// Configuration file to use.
+    #[clap(short, long, value_parser, default_value = "xdiff.yml")]
+    pub config: PathBuf,
+    /// Time between two runs, e.g. `30s` or `5m`.
+    #[clap(short, long, value_parser = humantime::parse_duration, default_value = "30s")]
+    pub interval: Duration,
+    /// Append a JSON line describing every run to this file.
+    #[clap(short, long, value_parser)]
+    pub log: Option<PathBuf>,
+    #[clap(flatten)]
+    pub diff: DiffArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct TuiArgs {
+    /// Configuration file to use. Updated skip rules are saved back to it, after copying the
+    /// original to e.g. `xdiff.yml.bak`.
+    #[clap(short, long, value_parser, default_value = "xdiff.yml")]
+    pub config: PathBuf,
+    /// Use the responses stored in this directory instead of sending the requests.
+    #[clap(long, value_parser)]
+    pub replay: Option<PathBuf>,
+    #[clap(flatten)]
+    pub diff: DiffArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ReqArgs {
+    /// Profile name.
+    #[clap(short, long, value_parser)]
+    pub profile: String,
+    /// Configuration file to use.
+    #[clap(short, long, value_parser, default_value = "xdiff.yml")]
+    pub config: PathBuf,
+    /// The side of the profile whose request is sent.
+    #[clap(short, long, value_enum, default_value_t = Side::Req1)]
+    pub side: Side,
+    /// Override a request field: `key=value` for a query parameter, `%key=value` for a header,
+    /// `@key=value` for a body field.
+    #[clap(short, long, value_parser)]
+    pub extra: Vec<Override>,
+    /// Print the responses as JSON, with bodies parsed if they are JSON.
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct BenchArgs {
+    /// Profile name.
+    #[clap(short, long, value_parser)]
+    pub profile: String,
+    /// Configuration file to use.
+    #[clap(long, value_parser, default_value = "xdiff.yml")]
+    pub config: PathBuf,
+    /// Requests sent to each side.
+    #[clap(short = 'n', long, value_parser, default_value_t = 100)]
+    pub requests: usize,
+    /// Pairs of requests in flight at once.
+    #[clap(short, long, value_parser, default_value_t = 10)]
+    pub concurrency: usize,
+    /// Print the results as JSON.
+    #[clap(long)]
+    pub json: bool,
+    #[clap(flatten)]
+    pub diff: DiffArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct MockArgs {
+    /// Snapshot directory written by `xdiff run --record`.
+    #[clap(value_parser)]
+    pub dir: PathBuf,
+    /// Configuration file the snapshots were recorded with, mapping requests to them.
+    #[clap(short, long, value_parser, default_value = "xdiff.yml")]
+    pub config: PathBuf,
+    /// The side whose responses are served.
+    #[clap(short, long, value_enum, default_value_t = Side::Req1)]
+    pub side: Side,
+    /// Address to listen on.
+    #[clap(short, long, value_parser, default_value = "127.0.0.1:8080")]
+    pub addr: SocketAddr,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ValidateArgs {
+    /// Configuration file to check.
+    #[clap(short, long, value_parser, default_value = "xdiff.yml")]
+    pub config: PathBuf,
+    /// Also report skip rules matching nothing in the responses stored in this directory.
+    #[clap(long, value_parser, conflicts_with = "fetch")]
+    pub replay: Option<PathBuf>,
+    /// Also report skip rules matching nothing in the live responses.
+    #[clap(long)]
+    pub fetch: bool,
+}
+
+fn parse_day(day: &str) -> anyhow::Result<String> {
+    crate::baseline::parse_day(day)?;
+    Ok(day.to_string())
+}
@@ -0,0 +1,277 @@
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+use crate::{
+    jsonpath::{JsonPath, Segment},
+    ResponseProfile,
+};
+
+/// Normalizes two JSON bodies against each other so that differences the profile tolerates
+/// render identically in the text diff.
+pub(crate) struct Normalizer {
+    float_epsilon: Option<f64>,
+    unordered_arrays: Vec<(JsonPath, Option<String>)>,
+    ignore_null_vs_missing: bool,
+}
+
+impl Normalizer {
+    pub fn new(profile: &ResponseProfile) -> Result<Self> {
+        let unordered_arrays = profile
+            .unordered_arrays
+            .iter()
+            .map(|u| Ok((u.path().parse()?, u.key().map(|k| k.to_string()))))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            float_epsilon: profile.float_epsilon,
+            unordered_arrays,
+            ignore_null_vs_missing: profile.ignore_null_vs_missing,
+        })
+    }
+
+    pub fn normalize(&self, left: &mut Value, right: &mut Value) {
+        let mut path = Vec::new();
+        self.visit(left, right, &mut path);
+    }
+
+    fn visit(&self, left: &mut Value, right: &mut Value, path: &mut Vec<Segment>) {
+        match (left, right) {
+            (Value::Number(a), Value::Number(b)) => {
+                if let (Some(eps), Some(x), Some(y)) = (self.float_epsilon, a.as_f64(), b.as_f64())
+                {
+                    if (x - y).abs() <= eps {
+                        *b = a.clone();
+                    }
+                }
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                if self.ignore_null_vs_missing {
+                    a.retain(|k, v| !v.is_null() || b.contains_key(k));
+                    b.retain(|k, v| !v.is_null() || a.contains_key(k));
+                }
+                for (k, va) in a.iter_mut() {
+                    if let Some(vb) = b.get_mut(k) {
+                        path.push(Segment::Key(k.clone()));
+                        self.visit(va, vb, path);
+                        path.pop();
+                    }
+                }
+            }
+            (Value::Array(a), Value::Array(b)) => {
+                if let Some(key) = self.unordered_key(path) {
+                    self.align(a, b, key, path);
+                }
+                for (i, (va, vb)) in a.iter_mut().zip(b.iter_mut()).enumerate() {
+                    path.push(Segment::Index(i));
+                    self.visit(va, vb, path);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn unordered_key(&self, path: &[Segment]) -> Option<Option<&str>> {
+        self.unordered_arrays
+            .iter()
+            .find(|(p, _)| p.matches(path))
+            .map(|(_, key)| key.as_deref())
+    }
+
+    /// Reorder `right` to follow the order of `left`. Items without a counterpart are kept at
+    /// the end in their original order.
+    fn align(&self, left: &[Value], right: &mut Vec<Value>, key: Option<&str>, path: &[Segment]) {
+        let order = self.order(left, right, key, &mut path.to_vec());
+        let mut pool: Vec<Option<Value>> = right.drain(..).map(Some).collect();
+        *right = order.into_iter().filter_map(|j| pool[j].take()).collect();
+    }
+
+    /// The indexes of `right` in the order of `left`: for each left item the first unused right
+    /// item that is the same, then the unused ones.
+    fn order(
+        &self,
+        left: &[Value],
+        right: &[Value],
+        key: Option<&str>,
+        path: &mut Vec<Segment>,
+    ) -> Vec<usize> {
+        let mut used = vec![false; right.len()];
+        let mut order = Vec::with_capacity(right.len());
+        for (i, l) in left.iter().enumerate() {
+            path.push(Segment::Index(i));
+            let found =
+                (0..right.len()).find(|&j| !used[j] && self.same_item(l, &right[j], key, path));
+            path.pop();
+            if let Some(j) = found {
+                used[j] = true;
+                order.push(j);
+            }
+        }
+        order.extend((0..right.len()).filter(|&j| !used[j]));
+        order
+    }
+
+    fn same_item(&self, l: &Value, r: &Value, key: Option<&str>, path: &mut Vec<Segment>) -> bool {
+        match key {
+            Some(key) => match (l.get(key), r.get(key)) {
+                (Some(a), Some(b)) => {
+                    path.push(Segment::Key(key.to_string()));
+                    let same = self.equivalent(a, b, path);
+                    path.pop();
+                    same
+                }
+                _ => false,
+            },
+            None => self.equivalent(l, r, path),
+        }
+    }
+
+    /// Whether the two values would render the same once normalized, without normalizing them.
+    fn equivalent(&self, l: &Value, r: &Value, path: &mut Vec<Segment>) -> bool {
+        match (l, r) {
+            (Value::Number(a), Value::Number(b)) => {
+                a == b
+                    || matches!(
+                        (self.float_epsilon, a.as_f64(), b.as_f64()),
+                        (Some(eps), Some(x), Some(y)) if (x - y).abs() <= eps
+                    )
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                let dropped = |k: &String, v: &Value, other: &Map<String, Value>| {
+                    self.ignore_null_vs_missing && v.is_null() && !other.contains_key(k)
+                };
+                b.iter().all(|(k, v)| dropped(k, v, a) || a.contains_key(k))
+                    && a.iter().all(|(k, va)| {
+                        if dropped(k, va, b) {
+                            return true;
+                        }
+                        let Some(vb) = b.get(k) else {
+                            return false;
+                        };
+                        path.push(Segment::Key(k.clone()));
+                        let same = self.equivalent(va, vb, path);
+                        path.pop();
+                        same
+                    })
+            }
+            (Value::Array(a), Value::Array(b)) => {
+                if a.len() != b.len() {
+                    return false;
+                }
+                let order = match self.unordered_key(path) {
+                    Some(key) => self.order(a, b, key, path),
+                    None => (0..b.len()).collect(),
+                };
+                a.iter().zip(order).enumerate().all(|(i, (va, j))| {
+                    path.push(Segment::Index(i));
+                    let same = self.equivalent(va, &b[j], path);
+                    path.pop();
+                    same
+                })
+            }
+            _ => l == r,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn normalizer(unordered: &[(&str, Option<&str>)]) -> Normalizer {
+        Normalizer {
+            float_epsilon: None,
+            unordered_arrays: unordered
+                .iter()
+                .map(|(path, key)| (path.parse().unwrap(), key.map(str::to_string)))
+                .collect(),
+            ignore_null_vs_missing: false,
+        }
+    }
+
+    fn normalize(normalizer: &Normalizer, mut left: Value, mut right: Value) -> Value {
+        normalizer.normalize(&mut left, &mut right);
+        right
+    }
+
+    #[test]
+    fn close_floats_should_take_the_left_value() {
+        let normalizer = Normalizer {
+            float_epsilon: Some(0.01),
+            ..normalizer(&[])
+        };
+        let right = normalize(
+            &normalizer,
+            json!({ "a": 1.0, "b": [2.5] }),
+            json!({ "a": 1.005, "b": [2.6] }),
+        );
+        assert_eq!(right, json!({ "a": 1.0, "b": [2.6] }));
+    }
+
+    #[test]
+    fn nulls_should_match_missing_keys() {
+        let normalizer = Normalizer {
+            ignore_null_vs_missing: true,
+            ..normalizer(&[])
+        };
+        let (mut left, mut right) = (
+            json!({ "a": null, "b": null, "c": 1 }),
+            json!({ "b": 2, "c": 1, "d": null }),
+        );
+        normalizer.normalize(&mut left, &mut right);
+        assert_eq!(left, json!({ "b": null, "c": 1 }));
+        assert_eq!(right, json!({ "b": 2, "c": 1 }));
+    }
+
+    #[test]
+    fn unordered_arrays_should_follow_the_left_order() {
+        let normalizer = normalizer(&[("$.tags", None)]);
+        let right = normalize(
+            &normalizer,
+            json!({ "tags": ["a", "b", "c"] }),
+            json!({ "tags": ["d", "c", "a", "b"] }),
+        );
+        // Items without a counterpart go last.
+        assert_eq!(right, json!({ "tags": ["a", "b", "c", "d"] }));
+
+        // Arrays elsewhere keep their order.
+        let right = normalize(
+            &normalizer,
+            json!({ "ids": [1, 2] }),
+            json!({ "ids": [2, 1] }),
+        );
+        assert_eq!(right, json!({ "ids": [2, 1] }));
+    }
+
+    #[test]
+    fn keyed_arrays_should_align_items_by_key() {
+        let normalizer = normalizer(&[("$.users", Some("id"))]);
+        let right = normalize(
+            &normalizer,
+            json!({ "users": [{ "id": 1, "name": "a" }, { "id": 2, "name": "b" }] }),
+            json!({ "users": [{ "id": 2, "name": "B" }, { "id": 1, "name": "a" }] }),
+        );
+        assert_eq!(
+            right,
+            json!({ "users": [{ "id": 1, "name": "a" }, { "id": 2, "name": "B" }] })
+        );
+    }
+
+    #[test]
+    fn items_should_match_after_their_own_normalization() {
+        let normalizer = Normalizer {
+            float_epsilon: Some(0.1),
+            ..normalizer(&[("$.groups", None), ("$.groups[*].ids", None)])
+        };
+        let right = normalize(
+            &normalizer,
+            json!({ "groups": [{ "ids": [1, 2], "w": 1.0 }, { "ids": [3], "w": 2.0 }] }),
+            json!({ "groups": [{ "ids": [3], "w": 2.05 }, { "ids": [2, 1], "w": 0.95 }] }),
+        );
+        assert_eq!(
+            right,
+            json!({ "groups": [{ "ids": [1, 2], "w": 1.0 }, { "ids": [3], "w": 2.0 }] })
+        );
+    }
+}
@@ -1,46 +1,191 @@
-use std::collections::HashMap;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
-use reqwest::{header::HeaderMap, Method, Url};
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use similar::Algorithm;
 use tokio::fs;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use crate::{
+    assertion::{Check, SchemaCache},
+    compare::Normalizer,
+    diff_text,
+    jsonpath::JsonPath,
+    limit::{LimitConfig, Limiter},
+    matrix::{case_suffix, MatrixProfile},
+    redact::RedactConfig,
+    req::Override,
+    stream,
+    transform::Transform,
+    RequestProfile, ResponseData,
+};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct DiffConfig {
+    #[serde(skip_serializing_if = "RedactConfig::is_empty", default)]
+    pub redact: RedactConfig,
+    #[serde(skip_serializing_if = "LimitConfig::is_empty", default)]
+    pub limits: LimitConfig,
     #[serde(flatten)]
-    pub profiles: HashMap<String, DiffProfile>,
+    pub profiles: BTreeMap<String, DiffProfile>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct DiffProfile {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub matrix: Option<MatrixProfile>,
     pub req1: RequestProfile,
     pub req2: RequestProfile,
     pub res: ResponseProfile,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct RequestProfile {
-    #[serde(with = "http_serde::method", default)]
-    pub method: Method,
-    pub url: Url,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub params: Option<serde_json::Value>,
-    #[serde(
-        skip_serializing_if = "HeaderMap::is_empty",
-        with = "http_serde::header_map",
-        default
-    )]
-    pub headers: HeaderMap,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub body: Option<serde_json::Value>,
+/// One concrete run of a profile: the profile itself, or one combination of its matrix.
+#[derive(Debug, Clone)]
+pub struct DiffCase {
+    pub name: String,
+    pub profile: DiffProfile,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct ResponseProfile {
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub skip_headers: Vec<String>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub skip_body: Vec<String>,
+    /// How the headers that aren't skipped are compared and what they must be.
+    #[serde(skip_serializing_if = "HeaderRules::is_empty", default)]
+    pub headers: HeaderRules,
+    /// Checks each response must pass on its own, whether or not the two differ.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub assert: Vec<Assertion>,
+    /// Leave the protocol version out of the status line, so that e.g. HTTP/1.1 and HTTP/2
+    /// responses can be equal. A mismatch is still noted in the report.
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub skip_version: bool,
+    /// jq filters reshaping each side's JSON body before skip rules and comparison.
+    #[serde(skip_serializing_if = "TransformProfile::is_empty", default)]
+    pub transform: TransformProfile,
+    /// Numbers closer than this are treated as equal, e.g. `1.0` and `1`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub float_epsilon: Option<f64>,
+    /// Arrays (by JSONPath) whose element order doesn't matter.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub unordered_arrays: Vec<UnorderedArray>,
+    /// Treat `"field": null` the same as a missing `field`.
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub ignore_null_vs_missing: bool,
+    /// How the filtered responses are diffed and rendered.
+    #[serde(skip_serializing_if = "DiffOptions::is_default", default)]
+    pub diff: DiffOptions,
+    /// Read at most this many bytes of each HTTP body, ignoring the rest.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_body_size: Option<u64>,
+    /// Compare HTTP bodies chunk by chunk as they arrive instead of buffering them, keeping
+    /// only windows around the regions where they differ. Assertions about the body are
+    /// skipped, as they would only see those windows.
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub stream: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct DiffOptions {
+    #[serde(default)]
+    pub algorithm: DiffAlgorithm,
+    #[serde(default)]
+    pub granularity: Granularity,
+    /// Unchanged lines shown around each change.
+    #[serde(default = "default_context")]
+    pub context: usize,
+    #[serde(default)]
+    pub ignore_whitespace: bool,
+    #[serde(default)]
+    pub ignore_case: bool,
+}
+
+#[derive(
+    Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffAlgorithm {
+    #[default]
+    Myers,
+    Patience,
+    Lcs,
+}
+
+#[derive(
+    Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    #[default]
+    Line,
+    Word,
+    Char,
+}
+
+fn default_context() -> usize {
+    3
+}
+
+/// Either an expression comparing `status`, `version`, or the nodes a JSONPath selects in the
+/// body (`.length` for their length) with a value, e.g. `$.items.length > 0`, or `schema`, a
+/// JSON Schema file the body must match, relative to the config file.
+#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[serde(untagged)]
+pub enum Assertion {
+    Expr(#[schemars(with = "String")] Check),
+    Schema {
+        schema: PathBuf,
+        /// The directory of the config file, set when it's loaded.
+        #[serde(skip)]
+        base: Option<PathBuf>,
+        #[serde(skip)]
+        validator: SchemaCache,
+    },
+}
+
+/// Header values are always compared ignoring whitespace around separators, outside of quoted
+/// strings, and repeated headers as lists of values in the order received.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct HeaderRules {
+    /// Repeated headers whose values are compared as a set instead, e.g. `set-cookie`.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub sets: Vec<String>,
+    /// Headers whose comma-separated items, and the `;` parameters of each item, may come in
+    /// any order, e.g. `cache-control`.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub unordered: Vec<String>,
+    /// Headers both sides must have, with this value, or any value when null.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub require: BTreeMap<String, Option<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct TransformProfile {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub req1: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub req2: Option<String>,
+}
+
+/// An array compared regardless of order. Either a bare JSONPath, or a path plus the field that
+/// identifies matching elements on both sides.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(untagged)]
+pub enum UnorderedArray {
+    Path(String),
+    Keyed {
+        path: String,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        key: Option<String>,
+    },
 }
 
 impl DiffConfig {
@@ -53,15 +198,448 @@ impl DiffConfig {
         Ok(serde_yaml::from_str(content)?)
     }
 
+    pub fn to_yaml(&self) -> Result<String> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(content: &str) -> Result<Self> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    /// Load a config file, parsed as TOML or JSON according to its extension and as YAML
+    /// otherwise.
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let config = match ConfigFormat::of(path) {
+            ConfigFormat::Yaml => Self::from_yaml(&content),
+            ConfigFormat::Toml => Self::from_toml(&content),
+            ConfigFormat::Json => Self::from_json(&content),
+        };
+        let mut config = config.with_context(|| format!("failed to parse {}", path.display()))?;
+        config.set_base(path.parent().unwrap_or(Path::new("")));
+        Limiter::new(&config.limits)
+            .with_context(|| format!("invalid limits in {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Resolve the relative paths of the config, like matrix CSV files, against `dir`.
+    pub fn set_base(&mut self, dir: &Path) {
+        for profile in self.profiles.values_mut() {
+            if let Some(matrix) = &mut profile.matrix {
+                matrix.base = Some(dir.to_path_buf());
+            }
+            for req in [&mut profile.req1, &mut profile.req2] {
+                if let Some(graphql) = &mut req.graphql {
+                    graphql.base = Some(dir.to_path_buf());
+                }
+                if let Some(grpc) = &mut req.grpc {
+                    grpc.base = Some(dir.to_path_buf());
+                }
+            }
+            for assertion in &mut profile.res.assert {
+                if let Assertion::Schema { base, .. } = assertion {
+                    *base = Some(dir.to_path_buf());
+                }
+            }
+        }
+    }
+
+    /// Serialize in the format that [`DiffConfig::load`] would read from `path`.
+    pub fn to_string_for(&self, path: impl AsRef<Path>) -> Result<String> {
+        match ConfigFormat::of(path.as_ref()) {
+            ConfigFormat::Yaml => self.to_yaml(),
+            ConfigFormat::Toml => self.to_toml(),
+            ConfigFormat::Json => self.to_json(),
+        }
+    }
+
+    /// A JSON Schema describing the config file, for editors to validate against.
+    pub fn json_schema() -> Result<String> {
+        Ok(serde_json::to_string_pretty(&schemars::schema_for!(DiffConfig))? + "\n")
+    }
+
     pub fn get_profile(&self, name: &str) -> Option<&DiffProfile> {
         self.profiles.get(name)
     }
 }
 
-pub struct DiffArgs;
+pub(crate) enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    pub(crate) fn of(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::Toml,
+            Some("json") => Self::Json,
+            _ => Self::Yaml,
+        }
+    }
+}
+
+/// Options given on the command line, overriding those of the profile.
+#[derive(Debug, Clone, Default, Args)]
+pub struct DiffArgs {
+    /// Diff algorithm.
+    #[clap(long, value_enum)]
+    pub algorithm: Option<DiffAlgorithm>,
+    /// Diff granularity.
+    #[clap(long, value_enum)]
+    pub granularity: Option<Granularity>,
+    /// Unchanged lines shown around each change.
+    #[clap(long, value_parser)]
+    pub context: Option<usize>,
+    /// Ignore whitespace when comparing.
+    #[clap(long)]
+    pub ignore_whitespace: bool,
+    /// Ignore case when comparing.
+    #[clap(long)]
+    pub ignore_case: bool,
+    /// Override a request field on both sides: `key=value` for a query parameter, `%key=value`
+    /// for a header, `@key=value` for a body field.
+    #[clap(short, long, value_parser)]
+    pub extra: Vec<Override>,
+    /// Paces the requests sent with these args, as set up from the config's `limits`.
+    #[clap(skip)]
+    pub limiter: Limiter,
+}
+
+impl DiffArgs {
+    /// The profile's diff options with these overrides applied.
+    pub fn options(&self, base: &DiffOptions) -> DiffOptions {
+        DiffOptions {
+            algorithm: self.algorithm.unwrap_or(base.algorithm),
+            granularity: self.granularity.unwrap_or(base.granularity),
+            context: self.context.unwrap_or(base.context),
+            ignore_whitespace: self.ignore_whitespace || base.ignore_whitespace,
+            ignore_case: self.ignore_case || base.ignore_case,
+        }
+    }
+}
 
 impl DiffProfile {
-    pub async fn diff(&self, _args: DiffArgs) -> Result<String> {
-        todo!()
+    /// Expand the matrix, if any, into one case per combination.
+    pub fn cases(&self, name: &str) -> Result<Vec<DiffCase>> {
+        let matrix = match &self.matrix {
+            Some(matrix) => matrix,
+            None => {
+                return Ok(vec![DiffCase {
+                    name: name.to_string(),
+                    profile: self.clone(),
+                }])
+            }
+        };
+        matrix
+            .combinations()?
+            .iter()
+            .map(|vars| {
+                Ok(DiffCase {
+                    name: format!("{}{}", name, case_suffix(vars)),
+                    profile: DiffProfile {
+                        matrix: None,
+                        req1: self.req1.interpolate(vars)?,
+                        req2: self.req2.interpolate(vars)?,
+                        res: self.res.clone(),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    pub async fn diff(&self, args: DiffArgs) -> Result<String> {
+        let (res1, res2) = self.fetch(&args).await?;
+        self.diff_responses(&args, &res1, &res2)
+    }
+
+    /// Send both requests concurrently.
+    pub async fn fetch(&self, args: &DiffArgs) -> Result<(ResponseData, ResponseData)> {
+        if self.res.stream {
+            return stream::fetch(self, args).await;
+        }
+        let limit = self.res.max_body_size;
+        tokio::try_join!(
+            self.req1.send_limited(args, limit),
+            self.req2.send_limited(args, limit)
+        )
+    }
+
+    /// Diff two responses that were already received, e.g. replayed from a snapshot. Differing
+    /// regions a streamed body left out are counted at the end, as the marker for them in the
+    /// bodies is equal on both sides.
+    pub fn diff_responses(
+        &self,
+        args: &DiffArgs,
+        res1: &ResponseData,
+        res2: &ResponseData,
+    ) -> Result<String> {
+        let (text1, text2) = self.filter_text(res1, res2)?;
+        let mut diff = diff_text(&text1, &text2, &args.options(&self.res.diff))?;
+        let hidden = [res1, res2]
+            .iter()
+            .filter_map(|res| res.truncated.map(|t| t.hidden_regions))
+            .max()
+            .unwrap_or_default();
+        if hidden > 0 {
+            diff.push_str(&format!(
+                "[... {} more differing regions not shown ...]\n",
+                hidden
+            ));
+        }
+        Ok(diff)
+    }
+
+    /// Render both responses as comparable text, as configured by `res`.
+    pub fn filter_text(
+        &self,
+        res1: &ResponseData,
+        res2: &ResponseData,
+    ) -> Result<(String, String)> {
+        let graphql = self.req1.graphql.is_some() || self.req2.graphql.is_some();
+        self.res.filter_text(res1, res2, graphql)
+    }
+}
+
+impl ResponseProfile {
+    /// Render both responses as comparable text, dropping skipped headers and body fields and
+    /// applying the transforms and tolerant comparison options to JSON bodies. GraphQL bodies
+    /// are rendered with their `errors` apart from their `data`.
+    pub fn filter_text(
+        &self,
+        res1: &ResponseData,
+        res2: &ResponseData,
+        graphql: bool,
+    ) -> Result<(String, String)> {
+        let mut body1 = self.parse_body(res1, self.transform.req1.as_deref())?;
+        let mut body2 = self.parse_body(res2, self.transform.req2.as_deref())?;
+        if let (Some(v1), Some(v2)) = (&mut body1, &mut body2) {
+            Normalizer::new(self)?.normalize(v1, v2);
+        }
+        let (headers1, headers2) =
+            self.headers
+                .render(&res1.headers, &res2.headers, &self.skip_headers);
+        Ok((
+            self.render(res1, &headers1, body1.as_ref(), graphql)?,
+            self.render(res2, &headers2, body2.as_ref(), graphql)?,
+        ))
+    }
+
+    /// The required headers and assertions each response fails, prefixed by its side.
+    pub async fn violations(
+        &self,
+        res1: &ResponseData,
+        res2: &ResponseData,
+    ) -> Result<Vec<String>> {
+        let mut violations = Vec::new();
+        for (side, res) in [("req1", res1), ("req2", res2)] {
+            let mut found = self.headers.violations(&res.headers);
+            for assertion in &self.assert {
+                if self.stream && assertion.reads_body() {
+                    continue;
+                }
+                found.extend(assertion.violations(res).await?);
+            }
+            violations.extend(found.into_iter().map(|v| format!("{}: {}", side, v)));
+        }
+        Ok(violations)
+    }
+
+    /// Skip rules that match nothing in any of the given responses, as the field they are listed
+    /// under and their index in it.
+    pub(crate) fn unused_skip_rules(
+        &self,
+        responses: &[(ResponseData, ResponseData)],
+    ) -> Result<Vec<(&'static str, usize)>> {
+        let mut headers = vec![false; self.skip_headers.len()];
+        let mut body = vec![false; self.skip_body.len()];
+        for (res1, res2) in responses {
+            for (res, transform) in [
+                (res1, self.transform.req1.as_deref()),
+                (res2, self.transform.req2.as_deref()),
+            ] {
+                for (used, name) in headers.iter_mut().zip(&self.skip_headers) {
+                    *used |= res.headers.contains_key(name.as_str());
+                }
+                let value = match Self::transformed_body(res, transform)? {
+                    Some(v) => v,
+                    None => continue,
+                };
+                for (used, skip) in body.iter_mut().zip(&self.skip_body) {
+                    *used |= if skip.starts_with('$') {
+                        !skip.parse::<JsonPath>()?.select(&value).is_empty()
+                    } else {
+                        value.get(skip).is_some()
+                    };
+                }
+            }
+        }
+        let unused = |field, used: Vec<bool>| {
+            used.into_iter()
+                .enumerate()
+                .filter(|(_, used)| !used)
+                .map(move |(idx, _)| (field, idx))
+        };
+        Ok(unused("skip_headers", headers)
+            .chain(unused("skip_body", body))
+            .collect())
+    }
+
+    fn parse_body(&self, res: &ResponseData, transform: Option<&str>) -> Result<Option<Value>> {
+        let mut value = match Self::transformed_body(res, transform)? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        for skip in &self.skip_body {
+            if skip.starts_with('$') {
+                skip.parse::<JsonPath>()?.remove(&mut value);
+            } else if let Value::Object(map) = &mut value {
+                map.remove(skip);
+            }
+        }
+        Ok(Some(value))
+    }
+
+    fn transformed_body(res: &ResponseData, transform: Option<&str>) -> Result<Option<Value>> {
+        if !res.is_json() {
+            return Ok(None);
+        }
+        let mut value: Value = match serde_json::from_str(&res.body) {
+            Ok(v) => v,
+            Err(_) => return Ok(None),
+        };
+        if let Some(code) = transform {
+            value = Transform::new(code)?.apply(value)?;
+        }
+        Ok(Some(value))
+    }
+
+    fn render(
+        &self,
+        res: &ResponseData,
+        headers: &[String],
+        body: Option<&Value>,
+        graphql: bool,
+    ) -> Result<String> {
+        let mut output = String::new();
+        match self.skip_version {
+            true => writeln!(&mut output, "{}", res.status)?,
+            false => writeln!(&mut output, "{:?} {}", res.version, res.status)?,
+        }
+        for line in headers {
+            writeln!(&mut output, "{}", line)?;
+        }
+        writeln!(&mut output)?;
+        match body {
+            Some(Value::Object(map)) if graphql => {
+                // `errors` first and apart from `data`, so that a failing field shows up as such
+                // rather than as a hole in the data.
+                let mut keys: Vec<_> = map.keys().collect();
+                keys.sort_by_key(|k| match k.as_str() {
+                    "errors" => 0,
+                    "data" => 1,
+                    _ => 2,
+                });
+                for (idx, key) in keys.into_iter().enumerate() {
+                    if idx > 0 {
+                        writeln!(&mut output)?;
+                    }
+                    writeln!(&mut output, "{}:", key)?;
+                    writeln!(&mut output, "{}", serde_json::to_string_pretty(&map[key])?)?;
+                }
+            }
+            Some(v) => writeln!(&mut output, "{}", serde_json::to_string_pretty(v)?)?,
+            None => writeln!(&mut output, "{}", res.body)?,
+        }
+        Ok(output)
+    }
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: DiffAlgorithm::default(),
+            granularity: Granularity::default(),
+            context: default_context(),
+            ignore_whitespace: false,
+            ignore_case: false,
+        }
+    }
+}
+
+impl DiffOptions {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// The key a line or token is compared by.
+    pub fn normalize(&self, s: &str) -> String {
+        let s = s.trim_end_matches('\n');
+        let s = if self.ignore_whitespace {
+            if s.chars().all(char::is_whitespace) && !s.is_empty() {
+                " ".to_string()
+            } else {
+                s.chars().filter(|c| !c.is_whitespace()).collect()
+            }
+        } else {
+            s.to_string()
+        };
+        if self.ignore_case {
+            s.to_lowercase()
+        } else {
+            s
+        }
+    }
+}
+
+impl From<DiffAlgorithm> for Algorithm {
+    fn from(algorithm: DiffAlgorithm) -> Self {
+        match algorithm {
+            DiffAlgorithm::Myers => Algorithm::Myers,
+            DiffAlgorithm::Patience => Algorithm::Patience,
+            DiffAlgorithm::Lcs => Algorithm::Lcs,
+        }
+    }
+}
+
+impl HeaderRules {
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty() && self.unordered.is_empty() && self.require.is_empty()
+    }
+}
+
+impl TransformProfile {
+    pub fn is_empty(&self) -> bool {
+        self.req1.is_none() && self.req2.is_none()
+    }
+}
+
+impl UnorderedArray {
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Path(path) | Self::Keyed { path, .. } => path,
+        }
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            Self::Path(_) => None,
+            Self::Keyed { key, .. } => key.as_deref(),
+        }
     }
 }
@@ -0,0 +1,264 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use prost_reflect::{
+    prost::Message, DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor,
+    SerializeOptions,
+};
+use reqwest::{
+    header::{self, HeaderMap, HeaderValue},
+    StatusCode, Url, Version,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tonic::{
+    client::Grpc,
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    codegen::http::uri::PathAndQuery,
+    metadata::{MetadataKey, MetadataValue},
+    transport::{Channel, ClientTlsConfig, Endpoint},
+    Request, Status,
+};
+
+use crate::{req::header_map, ResponseData};
+
+/// A unary gRPC call. Messages are written in the protobuf JSON mapping and described by a
+/// `.proto` file or a compiled descriptor set.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct GrpcProfile {
+    /// A `.proto` file defining the service. A relative path, like those of `includes` and
+    /// `descriptor_set`, is relative to the config file.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub proto: Option<PathBuf>,
+    /// Directories searched for the imports of `proto`, by default the directory of `proto`.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub includes: Vec<PathBuf>,
+    /// A file descriptor set, as written by `protoc --descriptor_set_out`, instead of `proto`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub descriptor_set: Option<PathBuf>,
+    /// The method to call, as `package.Service/Method`.
+    pub method: String,
+    /// The request message.
+    #[serde(skip_serializing_if = "Value::is_null", default)]
+    pub request: Value,
+    /// The directory of the config file, set when it's loaded.
+    #[serde(skip)]
+    pub base: Option<PathBuf>,
+    #[serde(skip)]
+    cache: GrpcCache,
+}
+
+/// The descriptors and connections of a profile, set up by its first call. Clones share them,
+/// so that the cases of a profile and repeated calls like those of `xdiff bench` reuse them.
+#[derive(Clone, Default)]
+struct GrpcCache {
+    inner: Arc<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    pool: Mutex<Option<DescriptorPool>>,
+    channels: Mutex<HashMap<Url, Channel>>,
+}
+
+impl GrpcProfile {
+    /// Call the method on the server at `url`, sending `headers` as metadata. The response
+    /// message is returned as a JSON body, and a non-OK status as a JSON body with its code and
+    /// message, so that both can be diffed like any other response.
+    pub(crate) async fn send(&self, url: &Url, headers: &HeaderMap) -> Result<ResponseData> {
+        let method = self.method_descriptor()?;
+        let request = match &self.request {
+            Value::Null => DynamicMessage::new(method.input()),
+            value => DynamicMessage::deserialize(method.input(), value)
+                .with_context(|| format!("invalid request for {}", self.method))?,
+        };
+        let mut request = Request::new(request);
+        for (name, value) in headers {
+            if name.as_str().ends_with("-bin") {
+                request.metadata_mut().insert_bin(
+                    MetadataKey::from_bytes(name.as_str().as_bytes())?,
+                    MetadataValue::from_bytes(value.as_bytes()),
+                );
+            } else {
+                request.metadata_mut().insert(
+                    MetadataKey::from_bytes(name.as_str().as_bytes())?,
+                    value.to_str()?.parse()?,
+                );
+            }
+        }
+
+        let channel = self.channel(url).await?;
+        let mut client = Grpc::new(channel);
+        client.ready().await?;
+        let path: PathAndQuery =
+            format!("/{}/{}", method.parent_service().full_name(), method.name()).parse()?;
+
+        let (metadata, body) = match client
+            .unary(request, path, DynamicCodec::new(method.output()))
+            .await
+        {
+            Ok(response) => {
+                let (metadata, message, _) = response.into_parts();
+                let options = SerializeOptions::new().skip_default_fields(false);
+                let body =
+                    message.serialize_with_options(serde_json::value::Serializer, &options)?;
+                (metadata, body)
+            }
+            Err(status) => (
+                status.metadata().clone(),
+                json!({ "code": format!("{:?}", status.code()), "message": status.message() }),
+            ),
+        };
+        let metadata = metadata.into_headers();
+        let mut headers = header_map(metadata.iter().map(|(k, v)| (k.as_str(), v.as_bytes())))?;
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/grpc+json"),
+        );
+        Ok(ResponseData {
+            status: StatusCode::OK,
+            version: Version::HTTP_2,
+            headers,
+            body: serde_json::to_string(&body)?,
+            truncated: None,
+        })
+    }
+
+    /// A channel to `url`, connected on first use.
+    async fn channel(&self, url: &Url) -> Result<Channel> {
+        if let Some(channel) = self.cache.inner.channels.lock().unwrap().get(url) {
+            return Ok(channel.clone());
+        }
+        let mut endpoint = Endpoint::from_shared(url.to_string())?;
+        if url.scheme() == "https" {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new().with_webpki_roots())?;
+        }
+        let channel = endpoint
+            .connect()
+            .await
+            .with_context(|| format!("failed to connect to {}", url))?;
+        let mut channels = self.cache.inner.channels.lock().unwrap();
+        Ok(channels.entry(url.clone()).or_insert(channel).clone())
+    }
+
+    /// The descriptors of `proto` or `descriptor_set`, read on first use.
+    fn pool(&self) -> Result<DescriptorPool> {
+        let mut pool = self.cache.inner.pool.lock().unwrap();
+        if let Some(pool) = &*pool {
+            return Ok(pool.clone());
+        }
+        let loaded = match (&self.proto, &self.descriptor_set) {
+            (Some(proto), None) => {
+                let proto = self.resolve(proto);
+                let includes = if self.includes.is_empty() {
+                    let dir = proto
+                        .parent()
+                        .filter(|p| !p.as_os_str().is_empty())
+                        .unwrap_or_else(|| Path::new("."));
+                    vec![dir.to_path_buf()]
+                } else {
+                    self.includes.iter().map(|dir| self.resolve(dir)).collect()
+                };
+                DescriptorPool::from_file_descriptor_set(protox::compile([proto], includes)?)?
+            }
+            (None, Some(path)) => {
+                let path = self.resolve(path);
+                DescriptorPool::decode(
+                    std::fs::read(&path)
+                        .with_context(|| format!("failed to read {}", path.display()))?
+                        .as_slice(),
+                )?
+            }
+            _ => bail!("grpc needs exactly one of proto and descriptor_set"),
+        };
+        Ok(pool.insert(loaded).clone())
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        match &self.base {
+            Some(base) => base.join(path),
+            None => path.to_path_buf(),
+        }
+    }
+
+    fn method_descriptor(&self) -> Result<MethodDescriptor> {
+        let pool = self.pool()?;
+        let (service, name) = self.method.rsplit_once('/').ok_or_else(|| {
+            anyhow!(
+                "grpc method must look like package.Service/Method, got {}",
+                self.method
+            )
+        })?;
+        let method = pool
+            .get_service_by_name(service)
+            .ok_or_else(|| anyhow!("service {} not found", service))?
+            .methods()
+            .find(|m| m.name() == name)
+            .ok_or_else(|| anyhow!("method {} not found in {}", name, service))?;
+        if method.is_client_streaming() || method.is_server_streaming() {
+            bail!(
+                "{} is a streaming method, only unary ones are supported",
+                self.method
+            );
+        }
+        Ok(method)
+    }
+}
+
+impl fmt::Debug for GrpcCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GrpcCache").finish_non_exhaustive()
+    }
+}
+
+/// A codec for messages only known at runtime, decoding them with the given descriptor.
+#[derive(Debug, Clone)]
+pub struct DynamicCodec(MessageDescriptor);
+
+impl DynamicCodec {
+    pub fn new(decode: MessageDescriptor) -> Self {
+        Self(decode)
+    }
+}
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = Self;
+    type Decoder = Self;
+
+    fn encoder(&mut self) -> Self {
+        self.clone()
+    }
+
+    fn decoder(&mut self) -> Self {
+        self.clone()
+    }
+}
+
+impl Encoder for DynamicCodec {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: DynamicMessage, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        item.encode(dst)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+impl Decoder for DynamicCodec {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<DynamicMessage>, Status> {
+        DynamicMessage::decode(self.0.clone(), src)
+            .map(Some)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
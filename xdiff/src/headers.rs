@@ -0,0 +1,148 @@
+use reqwest::header::{HeaderMap, HeaderName};
+
+use crate::HeaderRules;
+
+impl HeaderRules {
+    /// Render the headers of both sides as `name: value` lines, leaving out `skip`. Values that
+    /// the rules consider equal render identically on both sides.
+    pub(crate) fn render(
+        &self,
+        headers1: &HeaderMap,
+        headers2: &HeaderMap,
+        skip: &[String],
+    ) -> (Vec<String>, Vec<String>) {
+        (self.lines(headers1, skip), self.lines(headers2, skip))
+    }
+
+    /// The required headers missing from `headers` or with another value.
+    pub(crate) fn violations(&self, headers: &HeaderMap) -> Vec<String> {
+        let mut violations = Vec::new();
+        for (name, expected) in &self.require {
+            let name = name.to_ascii_lowercase();
+            let values = self.values(headers, &name);
+            match expected {
+                _ if values.is_empty() => violations.push(format!("header {} is missing", name)),
+                Some(expected) => {
+                    let expected = self.normalize(&name, expected);
+                    if !values.contains(&expected) {
+                        violations.push(format!(
+                            "header {} is {}, expected {}",
+                            name,
+                            values.join(", "),
+                            expected
+                        ));
+                    }
+                }
+                None => {}
+            }
+        }
+        violations
+    }
+
+    fn lines(&self, headers: &HeaderMap, skip: &[String]) -> Vec<String> {
+        headers
+            .keys()
+            .filter(|name| !contains(skip, name.as_str()))
+            .flat_map(|name| {
+                self.values(headers, name.as_str())
+                    .into_iter()
+                    .map(move |value| format!("{}: {}", name, value))
+            })
+            .collect()
+    }
+
+    /// The normalized values of a header, sorted and deduplicated if it's compared as a set.
+    fn values(&self, headers: &HeaderMap, name: &str) -> Vec<String> {
+        let name = match HeaderName::from_bytes(name.as_bytes()) {
+            Ok(name) => name,
+            Err(_) => return Vec::new(),
+        };
+        let mut values: Vec<String> = headers
+            .get_all(&name)
+            .iter()
+            .map(|v| self.normalize(name.as_str(), &String::from_utf8_lossy(v.as_bytes())))
+            .collect();
+        if contains(&self.sets, name.as_str()) {
+            values.sort();
+            values.dedup();
+        }
+        values
+    }
+
+    /// Collapse whitespace, with none around `=` and a single space after `,` and `;`, and sort
+    /// the items and their parameters of unordered headers. Quoted strings are kept as they are.
+    fn normalize(&self, name: &str, value: &str) -> String {
+        let unordered = contains(&self.unordered, name);
+        let mut items: Vec<String> = split_unquoted(value, ',')
+            .into_iter()
+            .map(|item| {
+                let mut params: Vec<String> = split_unquoted(item, ';')
+                    .into_iter()
+                    .map(|param| {
+                        split_unquoted(param, '=')
+                            .into_iter()
+                            .map(collapse_whitespace)
+                            .collect::<Vec<_>>()
+                            .join("=")
+                    })
+                    .collect();
+                if unordered && params.len() > 2 {
+                    params[1..].sort();
+                }
+                params.join("; ")
+            })
+            .collect();
+        if unordered {
+            items.sort();
+        }
+        items.join(", ").trim().to_string()
+    }
+}
+
+/// Split `value` at each `sep` that isn't inside a double-quoted string.
+fn split_unquoted(value: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (idx, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == sep && !quoted => {
+                parts.push(&value[start..idx]);
+                start = idx + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Trim `value` and collapse its runs of whitespace into one space, outside of double quotes.
+fn collapse_whitespace(value: &str) -> String {
+    let mut collapsed = String::with_capacity(value.len());
+    let (mut quoted, mut escaped, mut space) = (false, false, false);
+    for c in value.trim().chars() {
+        if c.is_whitespace() && !quoted {
+            space = true;
+            continue;
+        }
+        if space {
+            collapsed.push(' ');
+            space = false;
+        }
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ => {}
+        }
+        collapsed.push(c);
+    }
+    collapsed
+}
+
+fn contains(names: &[String], name: &str) -> bool {
+    names.iter().any(|n| n.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,291 @@
+use std::{fmt::Write, time::SystemTime};
+
+use anyhow::Result;
+use similar::{capture_diff_slices, DiffTag};
+
+use crate::{
+    utils::{escape_xml, tokenize},
+    CaseDetail, CaseResult, DiffOptions, Granularity, Outcome, Report,
+};
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 2em; color: #24292f; }
+h1 { font-size: 1.6em; }
+.summary span { display: inline-block; margin-right: 1.5em; }
+.case { border: 1px solid #d0d7de; border-radius: 6px; margin: 1.5em 0; padding: 0 1em 1em; }
+.badge { font-size: 0.75em; padding: 0.15em 0.6em; border-radius: 1em; color: #fff; vertical-align: middle; }
+.equal { background: #1a7f37; } .different { background: #cf222e; } .error { background: #9a6700; }
+table.meta { border-collapse: collapse; margin-bottom: 1em; }
+table.meta td, table.meta th { border: 1px solid #d0d7de; padding: 0.2em 0.6em; text-align: left; }
+table.diff { border-collapse: collapse; table-layout: fixed; width: 100%; font-family: SFMono-Regular, Consolas, monospace; font-size: 12px; }
+table.diff col.num { width: 3.5em; }
+table.diff td { padding: 0 0.4em; white-space: pre-wrap; word-break: break-all; vertical-align: top; }
+table.diff td.num { color: #6e7781; text-align: right; user-select: none; }
+td.del { background: #ffebe9; } td.ins { background: #e6ffec; } td.empty { background: #f6f8fa; }
+td.del mark { background: #ffc1c0; } td.ins mark { background: #abf2bc; }
+details summary { cursor: pointer; color: #0969da; font-size: 12px; padding: 0.2em 0.4em; background: #f6f8fa; }
+pre.error { background: #fff8c5; padding: 0.6em; white-space: pre-wrap; }
+p.note { background: #fff8c5; padding: 0.4em 0.6em; }
+p.violation { background: #ffebe9; padding: 0.4em 0.6em; }
+"#;
+
+/// Pieces of a line, and whether each is emphasized.
+type Pieces<'a> = Vec<(bool, &'a str)>;
+
+enum Cell<'a> {
+    /// A line by its index, in pieces.
+    Line(usize, Pieces<'a>),
+    Empty,
+}
+
+struct Row<'a> {
+    old: Cell<'a>,
+    new: Cell<'a>,
+    changed: bool,
+}
+
+impl Report {
+    /// A single static HTML page with a summary and a side-by-side diff of every case. All styles
+    /// are inlined so the file can be shared as is.
+    pub fn render_html(&self) -> Result<String> {
+        let mut html = String::new();
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now());
+        writeln!(html, "<!DOCTYPE html>")?;
+        writeln!(html, r#"<html lang="en"><head><meta charset="utf-8">"#)?;
+        writeln!(
+            html,
+            "<title>xdiff report</title><style>{}</style></head><body>",
+            STYLE
+        )?;
+        writeln!(html, "<h1>xdiff report</h1>")?;
+        writeln!(
+            html,
+            r#"<p class="summary"><span>Generated at {}</span><span>{} cases</span><span>{} equal</span><span>{} different</span><span>{} errors</span></p>"#,
+            timestamp,
+            self.cases.len(),
+            self.count(Outcome::is_equal),
+            self.count(|o| matches!(o, Outcome::Different { .. })),
+            self.count(|o| matches!(o, Outcome::Error { .. })),
+        )?;
+        for case in &self.cases {
+            render_case(&mut html, case)?;
+        }
+        writeln!(html, "</body></html>")?;
+        Ok(html)
+    }
+}
+
+fn render_case(html: &mut String, case: &CaseResult) -> Result<()> {
+    let (class, label) = match &case.outcome {
+        Outcome::Equal => ("equal", "equal"),
+        Outcome::Different { .. } => ("different", "different"),
+        Outcome::Error { .. } => ("error", "error"),
+    };
+    writeln!(html, r#"<section class="case">"#)?;
+    writeln!(
+        html,
+        r#"<h2>{} <span class="badge {}">{}</span></h2>"#,
+        escape_xml(&case.name),
+        class,
+        label
+    )?;
+    if let Outcome::Error { error } = &case.outcome {
+        writeln!(html, r#"<pre class="error">{}</pre>"#, escape_xml(error))?;
+    }
+    for note in &case.notes {
+        writeln!(html, r#"<p class="note">{}</p>"#, escape_xml(note))?;
+    }
+    for violation in &case.violations {
+        writeln!(
+            html,
+            r#"<p class="violation">{}</p>"#,
+            escape_xml(violation)
+        )?;
+    }
+    if let Some(detail) = &case.detail {
+        render_meta(html, detail)?;
+        render_diff(html, &detail.text1, &detail.text2, &detail.options)?;
+    }
+    writeln!(html, "</section>")?;
+    Ok(())
+}
+
+fn render_meta(html: &mut String, detail: &CaseDetail) -> Result<()> {
+    writeln!(
+        html,
+        r#"<table class="meta"><tr><th></th><th>Method</th><th>URL</th><th>Status</th><th>Version</th></tr>"#
+    )?;
+    for (side, req) in [("req1", &detail.req1), ("req2", &detail.req2)] {
+        writeln!(
+            html,
+            "<tr><th>{}</th><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            side,
+            escape_xml(&req.method),
+            escape_xml(&req.url),
+            req.status,
+            req.version
+        )?;
+    }
+    writeln!(html, "</table>")?;
+    Ok(())
+}
+
+fn render_diff(html: &mut String, text1: &str, text2: &str, options: &DiffOptions) -> Result<()> {
+    let rows = rows(text1, text2, options);
+
+    // Collapse runs of unchanged rows that are further than the context from any change.
+    let mut visible = vec![false; rows.len()];
+    for (idx, row) in rows.iter().enumerate() {
+        if row.changed {
+            let start = idx.saturating_sub(options.context);
+            let end = (idx + options.context + 1).min(rows.len());
+            visible[start..end].iter_mut().for_each(|v| *v = true);
+        }
+    }
+
+    let mut idx = 0;
+    while idx < rows.len() {
+        let shown = visible[idx];
+        let end = visible[idx..]
+            .iter()
+            .position(|v| *v != shown)
+            .map_or(rows.len(), |p| idx + p);
+        if shown {
+            render_rows(html, &rows[idx..end])?;
+        } else {
+            writeln!(
+                html,
+                "<details><summary>{} unchanged lines</summary>",
+                end - idx
+            )?;
+            render_rows(html, &rows[idx..end])?;
+            writeln!(html, "</details>")?;
+        }
+        idx = end;
+    }
+    Ok(())
+}
+
+/// Align the lines of both texts as the report's diff compares them: by the algorithm of the
+/// options, with the ignore options applied. With word or character granularity, lines
+/// compare token by token and the tokens that differ are emphasized.
+fn rows<'a>(text1: &'a str, text2: &'a str, options: &DiffOptions) -> Vec<Row<'a>> {
+    let old: Vec<&str> = text1.lines().collect();
+    let new: Vec<&str> = text2.lines().collect();
+    let old_keys: Vec<String> = old.iter().map(|l| line_key(l, options)).collect();
+    let new_keys: Vec<String> = new.iter().map(|l| line_key(l, options)).collect();
+
+    let mut rows = Vec::new();
+    for op in capture_diff_slices(options.algorithm.into(), &old_keys, &new_keys) {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        match tag {
+            DiffTag::Equal => {
+                for (i, j) in old_range.zip(new_range) {
+                    rows.push(Row {
+                        old: Cell::Line(i, vec![(false, old[i])]),
+                        new: Cell::Line(j, vec![(false, new[j])]),
+                        changed: false,
+                    });
+                }
+            }
+            _ => {
+                let len = old_range.len().max(new_range.len());
+                for k in 0..len {
+                    let i = (old_range.start + k < old_range.end).then_some(old_range.start + k);
+                    let j = (new_range.start + k < new_range.end).then_some(new_range.start + k);
+                    let (old, new) = match (i, j) {
+                        (Some(i), Some(j)) => {
+                            let (pieces1, pieces2) = emphasize(old[i], new[j], options);
+                            (Cell::Line(i, pieces1), Cell::Line(j, pieces2))
+                        }
+                        (Some(i), None) => (Cell::Line(i, vec![(true, old[i])]), Cell::Empty),
+                        (None, Some(j)) => (Cell::Empty, Cell::Line(j, vec![(true, new[j])])),
+                        (None, None) => unreachable!("k is below the longer range"),
+                    };
+                    rows.push(Row {
+                        old,
+                        new,
+                        changed: true,
+                    });
+                }
+            }
+        }
+    }
+    rows
+}
+
+/// The key a line is compared by: its normalized tokens with word or character granularity,
+/// so that lines are equal exactly when the token diff finds them equal.
+fn line_key(line: &str, options: &DiffOptions) -> String {
+    match options.granularity {
+        Granularity::Line => options.normalize(line),
+        granularity => tokenize(line, granularity)
+            .iter()
+            .map(|t| options.normalize(t))
+            .collect::<Vec<_>>()
+            .join("\0"),
+    }
+}
+
+/// Split two changed lines into pieces, marking those that differ. Lines are emphasized as a
+/// whole with line granularity.
+fn emphasize<'a>(
+    line1: &'a str,
+    line2: &'a str,
+    options: &DiffOptions,
+) -> (Pieces<'a>, Pieces<'a>) {
+    if options.granularity == Granularity::Line {
+        return (vec![(true, line1)], vec![(true, line2)]);
+    }
+    let old = tokenize(line1, options.granularity);
+    let new = tokenize(line2, options.granularity);
+    let old_keys: Vec<String> = old.iter().map(|t| options.normalize(t)).collect();
+    let new_keys: Vec<String> = new.iter().map(|t| options.normalize(t)).collect();
+    let (mut pieces1, mut pieces2) = (Vec::new(), Vec::new());
+    for op in capture_diff_slices(options.algorithm.into(), &old_keys, &new_keys) {
+        let changed = op.tag() != DiffTag::Equal;
+        pieces1.extend(old[op.old_range()].iter().map(|t| (changed, *t)));
+        pieces2.extend(new[op.new_range()].iter().map(|t| (changed, *t)));
+    }
+    (pieces1, pieces2)
+}
+
+fn render_rows(html: &mut String, rows: &[Row]) -> Result<()> {
+    writeln!(
+        html,
+        r#"<table class="diff"><colgroup><col class="num"><col><col class="num"><col></colgroup>"#
+    )?;
+    for row in rows {
+        write!(html, "<tr>")?;
+        render_cell(html, &row.old, row.changed, "del")?;
+        render_cell(html, &row.new, row.changed, "ins")?;
+        writeln!(html, "</tr>")?;
+    }
+    writeln!(html, "</table>")?;
+    Ok(())
+}
+
+fn render_cell(html: &mut String, cell: &Cell, changed: bool, class: &str) -> Result<()> {
+    match cell {
+        Cell::Line(idx, pieces) => {
+            let class = if changed { class } else { "" };
+            write!(
+                html,
+                r#"<td class="num">{}</td><td class="{}">"#,
+                idx + 1,
+                class
+            )?;
+            // Pieces are only marked when part of the line changed.
+            let partial = pieces.iter().any(|(emphasized, _)| !emphasized);
+            for (emphasized, text) in pieces {
+                match emphasized & partial {
+                    true => write!(html, "<mark>{}</mark>", escape_xml(text))?,
+                    false => write!(html, "{}", escape_xml(text))?,
+                }
+            }
+            write!(html, "</td>")?;
+        }
+        Cell::Empty => write!(html, r#"<td class="num"></td><td class="empty"></td>"#)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,315 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+
+/// One step of a JSONPath expression, or of the concrete location of a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+    /// `*` or `[*]`: any single key or index.
+    Wildcard,
+    /// `..`: zero or more levels.
+    Descendant,
+}
+
+/// A small JSONPath subset: `$`, `.name`, `['name']`, `[0]`, `[*]`, `.*` and `..name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath(Vec<Segment>);
+
+impl JsonPath {
+    pub fn segments(&self) -> &[Segment] {
+        &self.0
+    }
+
+    /// Check whether the concrete location `path` (only keys and indexes) is addressed by this
+    /// expression.
+    pub fn matches(&self, path: &[Segment]) -> bool {
+        matches_at(&self.0, path)
+    }
+
+    /// Collect every node addressed by this expression.
+    pub fn select<'a>(&self, value: &'a Value) -> Vec<&'a Value> {
+        let mut found = Vec::new();
+        let mut path = Vec::new();
+        self.collect(value, &mut path, &mut found);
+        found
+    }
+
+    /// Remove every node addressed by this expression, returning how many were removed. The
+    /// root itself is never removed.
+    pub fn remove(&self, value: &mut Value) -> usize {
+        let mut path = Vec::new();
+        self.remove_at(value, &mut path)
+    }
+
+    fn collect<'a>(&self, value: &'a Value, path: &mut Vec<Segment>, found: &mut Vec<&'a Value>) {
+        if self.matches(path) {
+            found.push(value);
+        }
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map {
+                    path.push(Segment::Key(k.clone()));
+                    self.collect(v, path, found);
+                    path.pop();
+                }
+            }
+            Value::Array(items) => {
+                for (i, v) in items.iter().enumerate() {
+                    path.push(Segment::Index(i));
+                    self.collect(v, path, found);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn remove_at(&self, value: &mut Value, path: &mut Vec<Segment>) -> usize {
+        let mut removed = 0;
+        match value {
+            Value::Object(map) => {
+                let keys: Vec<String> = map.keys().cloned().collect();
+                for k in keys {
+                    path.push(Segment::Key(k.clone()));
+                    if self.matches(path) {
+                        map.remove(&k);
+                        removed += 1;
+                    } else if let Some(v) = map.get_mut(&k) {
+                        removed += self.remove_at(v, path);
+                    }
+                    path.pop();
+                }
+            }
+            Value::Array(items) => {
+                let mut i = 0;
+                let mut original = 0;
+                while i < items.len() {
+                    path.push(Segment::Index(original));
+                    if self.matches(path) {
+                        items.remove(i);
+                        removed += 1;
+                    } else {
+                        removed += self.remove_at(&mut items[i], path);
+                        i += 1;
+                    }
+                    path.pop();
+                    original += 1;
+                }
+            }
+            _ => {}
+        }
+        removed
+    }
+}
+
+fn matches_at(pattern: &[Segment], path: &[Segment]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, None) => true,
+        (Some((Segment::Descendant, rest)), _) => {
+            matches_at(rest, path) || (!path.is_empty() && matches_at(pattern, &path[1..]))
+        }
+        (Some((p, rest)), Some((s, tail))) => {
+            let hit = match (p, s) {
+                (Segment::Wildcard, _) => true,
+                (Segment::Key(a), Segment::Key(b)) => a == b,
+                (Segment::Index(a), Segment::Index(b)) => a == b,
+                _ => false,
+            };
+            hit && matches_at(rest, tail)
+        }
+        _ => false,
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let rest = s
+            .trim()
+            .strip_prefix('$')
+            .ok_or_else(|| anyhow!("JSONPath `{}` must start with `$`", s))?;
+        let chars: Vec<char> = rest.chars().collect();
+        let mut segments = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '.' => {
+                    i += 1;
+                    if chars.get(i) == Some(&'.') {
+                        segments.push(Segment::Descendant);
+                        i += 1;
+                        if chars.get(i) == Some(&'[') {
+                            continue;
+                        }
+                    }
+                    let start = i;
+                    while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+                        i += 1;
+                    }
+                    let name: String = chars[start..i].iter().collect();
+                    match name.as_str() {
+                        "" => bail!("JSONPath `{}` has an empty segment", s),
+                        "*" => segments.push(Segment::Wildcard),
+                        _ => segments.push(Segment::Key(name)),
+                    }
+                }
+                '[' => {
+                    let end = chars[i..]
+                        .iter()
+                        .position(|c| *c == ']')
+                        .map(|p| p + i)
+                        .ok_or_else(|| anyhow!("JSONPath `{}` has an unclosed `[`", s))?;
+                    let inner: String = chars[i + 1..end].iter().collect();
+                    let inner = inner.trim();
+                    let segment = if inner == "*" {
+                        Segment::Wildcard
+                    } else if let Ok(idx) = inner.parse::<usize>() {
+                        Segment::Index(idx)
+                    } else if inner.len() >= 2
+                        && ((inner.starts_with('\'') && inner.ends_with('\''))
+                            || (inner.starts_with('"') && inner.ends_with('"')))
+                    {
+                        Segment::Key(inner[1..inner.len() - 1].to_string())
+                    } else {
+                        bail!("JSONPath `{}` has an invalid selector `[{}]`", s, inner);
+                    };
+                    segments.push(segment);
+                    i = end + 1;
+                }
+                c => bail!("JSONPath `{}` has an unexpected `{}`", s, c),
+            }
+        }
+        Ok(Self(segments))
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        let mut prev = None;
+        for segment in &self.0 {
+            match segment {
+                // Keys that wouldn't parse back in dotted form are quoted.
+                Segment::Key(k) if !is_plain_key(k) && k.contains('\'') => {
+                    write!(f, "[\"{}\"]", k)?
+                }
+                Segment::Key(k) if !is_plain_key(k) => write!(f, "['{}']", k)?,
+                Segment::Key(k) if prev == Some(&Segment::Descendant) => write!(f, "{}", k)?,
+                Segment::Key(k) => write!(f, ".{}", k)?,
+                Segment::Index(i) => write!(f, "[{}]", i)?,
+                Segment::Wildcard => write!(f, "[*]")?,
+                Segment::Descendant => write!(f, "..")?,
+            }
+            prev = Some(segment);
+        }
+        Ok(())
+    }
+}
+
+fn is_plain_key(key: &str) -> bool {
+    !key.is_empty()
+        && key != "*"
+        && !key.contains(|c: char| c == '.' || c == '[' || c == ']' || c.is_whitespace())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn key(k: &str) -> Segment {
+        Segment::Key(k.to_string())
+    }
+
+    #[test]
+    fn paths_should_parse_every_selector() {
+        let path: JsonPath = "$.data[*]['x-id'][2].*..name".parse().unwrap();
+        assert_eq!(
+            path.segments(),
+            [
+                key("data"),
+                Segment::Wildcard,
+                key("x-id"),
+                Segment::Index(2),
+                Segment::Wildcard,
+                Segment::Descendant,
+                key("name"),
+            ]
+        );
+        let path: JsonPath = r#"$["a.b"]..[0]"#.parse().unwrap();
+        assert_eq!(
+            path.segments(),
+            [key("a.b"), Segment::Descendant, Segment::Index(0)]
+        );
+        assert_eq!(path.to_string(), "$['a.b']..[0]");
+    }
+
+    #[test]
+    fn displayed_paths_should_parse_back() {
+        for path in [
+            "$.data[*].x-id[2]..name",
+            "$['a.b']..['c d'][0]",
+            "$..[\"it's.x\"]",
+            "$['*'].a['']",
+        ] {
+            let parsed: JsonPath = path.parse().unwrap();
+            assert_eq!(parsed.to_string(), path);
+            assert_eq!(parsed.to_string().parse::<JsonPath>().unwrap(), parsed);
+        }
+    }
+
+    #[test]
+    fn invalid_paths_should_be_errors() {
+        for (path, error) in [
+            ("data", "must start with `$`"),
+            ("$.a..", "empty segment"),
+            ("$[0", "unclosed `[`"),
+            ("$[x]", "invalid selector `[x]`"),
+            ("$a", "unexpected `a`"),
+        ] {
+            let e = path.parse::<JsonPath>().unwrap_err().to_string();
+            assert!(e.contains(error), "{}: {}", path, e);
+        }
+    }
+
+    #[test]
+    fn paths_should_match_concrete_locations() {
+        let path: JsonPath = "$..items[*].id".parse().unwrap();
+        assert!(path.matches(&[key("items"), Segment::Index(3), key("id")]));
+        assert!(path.matches(&[key("a"), key("items"), Segment::Index(0), key("id")]));
+        assert!(!path.matches(&[key("items"), key("id")]));
+        assert!("$".parse::<JsonPath>().unwrap().matches(&[]));
+    }
+
+    #[test]
+    fn select_and_remove_should_address_the_same_nodes() {
+        let mut value = json!({
+            "id": 1,
+            "items": [{ "id": 2, "name": "a" }, { "id": 3 }],
+            "meta": { "id": 4 }
+        });
+        let path: JsonPath = "$..id".parse().unwrap();
+        let selected: Vec<_> = path.select(&value).into_iter().cloned().collect();
+        assert_eq!(selected, [json!(1), json!(2), json!(3), json!(4)]);
+
+        assert_eq!(path.remove(&mut value), 4);
+        assert_eq!(value, json!({ "items": [{ "name": "a" }, {}], "meta": {} }));
+    }
+
+    #[test]
+    fn removing_array_items_should_use_their_original_indexes() {
+        // Once `[0]` is gone, `[3]` is still the second item, not the first.
+        let mut value = json!([[1, 2], [3]]);
+        assert_eq!("$..[0]".parse::<JsonPath>().unwrap().remove(&mut value), 2);
+        assert_eq!(value, json!([[]]));
+        let mut value = json!([0, 1, 2, 3]);
+        assert_eq!("$[*]".parse::<JsonPath>().unwrap().remove(&mut value), 4);
+        assert_eq!(value, json!([]));
+        assert_eq!("$".parse::<JsonPath>().unwrap().remove(&mut value), 0);
+    }
+}
@@ -1,3 +1,46 @@
+mod assertion;
+mod baseline;
+mod bench;
+pub mod cli;
+mod compare;
 mod config;
+pub mod grpc;
+mod headers;
+mod html;
+pub mod jsonpath;
+mod limit;
+mod matrix;
+pub mod mock;
+mod pretty;
+mod redact;
+mod report;
+mod req;
+mod script;
+pub mod signing;
+mod snapshot;
+mod stream;
+mod transform;
+pub mod tui;
+mod utils;
+mod validate;
+mod watch;
+mod websocket;
 
-pub use config::{DiffConfig, DiffProfile, RequestProfile, ResponseProfile};
+pub use baseline::{hunks, AcceptedDiff, Baseline, Review};
+pub use bench::{Bench, BenchReport, SideStats};
+pub use config::{
+    Assertion, DiffAlgorithm, DiffArgs, DiffCase, DiffConfig, DiffOptions, DiffProfile,
+    Granularity, HeaderRules, ResponseProfile, TransformProfile, UnorderedArray,
+};
+pub use limit::{LimitConfig, Limiter, Permit};
+pub use matrix::{MatrixProfile, MatrixValues};
+pub use redact::{RedactConfig, Redactor};
+pub use report::{CaseDetail, CaseResult, Outcome, OutputFormat, Report, RequestSummary};
+pub use req::{GraphqlProfile, HttpVersion, Override, RequestProfile, ResponseData, Truncated};
+pub use script::{Script, ScriptProfile};
+pub use snapshot::Snapshot;
+pub use transform::Transform;
+pub use utils::diff_text;
+pub use validate::{Problem, Validation};
+pub use watch::Watcher;
+pub use websocket::WebsocketProfile;
@@ -0,0 +1,163 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    fmt,
+    hash::{BuildHasher, Hasher},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use reqwest::Url;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::{self, Instant},
+};
+
+/// Limits on the requests sent, shared by every profile of the config.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default, PartialEq)]
+pub struct LimitConfig {
+    /// Requests started per second, across all hosts.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rps: Option<f64>,
+    /// Requests in flight at once to the same host and port.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub per_host: Option<usize>,
+    /// Delay each request by a random time up to this long, e.g. `200ms`, so that paced
+    /// requests don't arrive in lockstep.
+    #[serde(
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    #[schemars(with = "Option<String>")]
+    pub jitter: Option<Duration>,
+    /// Cases `xdiff run` and `xdiff accept` work on at once, 1 by default.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub concurrency: Option<usize>,
+}
+
+/// Paces requests as configured by [`LimitConfig`]. Clones share their state, so one limiter
+/// covers every request sent with the [`DiffArgs`](crate::DiffArgs) holding it.
+#[derive(Clone, Default)]
+pub struct Limiter {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    interval: Option<Duration>,
+    per_host: Option<usize>,
+    jitter: Option<Duration>,
+    next_slot: Mutex<Option<Instant>>,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+/// Held while requests are in flight, to count them against their hosts' caps.
+pub struct Permit {
+    _hosts: Vec<OwnedSemaphorePermit>,
+}
+
+impl LimitConfig {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl Limiter {
+    pub fn new(config: &LimitConfig) -> Result<Self> {
+        if let Some(rps) = config.rps {
+            if !rps.is_finite() || rps <= 0.0 {
+                bail!("rps must be a positive number, got {}", rps);
+            }
+        }
+        if config.rps.is_none() && config.per_host.is_none() && config.jitter.is_none() {
+            return Ok(Self::default());
+        }
+        let inner = Inner {
+            interval: config.rps.map(|rps| Duration::from_secs_f64(1.0 / rps)),
+            per_host: config.per_host,
+            jitter: config.jitter,
+            next_slot: Mutex::new(None),
+            hosts: Mutex::new(HashMap::new()),
+        };
+        Ok(Self {
+            inner: Some(Arc::new(inner)),
+        })
+    }
+
+    /// Wait until a request to `url` may start: a place under its host's cap first, then its
+    /// turn in the global pace, then the jitter.
+    pub async fn acquire(&self, url: &Url) -> Permit {
+        self.acquire_all(&[url]).await
+    }
+
+    /// Like [`acquire`](Self::acquire), for requests that stay in flight together. A host
+    /// shared by several of them is counted once, and hosts are taken in order, so that callers
+    /// waiting on each other's hosts can't deadlock.
+    pub async fn acquire_all(&self, urls: &[&Url]) -> Permit {
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            None => return Permit { _hosts: Vec::new() },
+        };
+        let mut permits = Vec::new();
+        if let Some(cap) = inner.per_host {
+            let mut hosts: Vec<_> = urls.iter().map(|url| host_key(url)).collect();
+            hosts.sort();
+            hosts.dedup();
+            for host in hosts {
+                let semaphore = inner
+                    .hosts
+                    .lock()
+                    .unwrap()
+                    .entry(host)
+                    .or_insert_with(|| Arc::new(Semaphore::new(cap.max(1))))
+                    .clone();
+                permits.extend(semaphore.acquire_owned().await.ok());
+            }
+        }
+        for _ in urls {
+            if let Some(interval) = inner.interval {
+                let slot = {
+                    let mut next = inner.next_slot.lock().unwrap();
+                    let slot = next.map_or(Instant::now(), |n| n.max(Instant::now()));
+                    *next = Some(slot + interval);
+                    slot
+                };
+                time::sleep_until(slot).await;
+            }
+            if let Some(jitter) = inner.jitter {
+                time::sleep(jitter.mul_f64(random_fraction())).await;
+            }
+        }
+        Permit { _hosts: permits }
+    }
+}
+
+impl fmt::Debug for Limiter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.inner {
+            Some(inner) => f
+                .debug_struct("Limiter")
+                .field("interval", &inner.interval)
+                .field("per_host", &inner.per_host)
+                .field("jitter", &inner.jitter)
+                .finish(),
+            None => write!(f, "Limiter(unlimited)"),
+        }
+    }
+}
+
+fn host_key(url: &Url) -> String {
+    format!(
+        "{}:{}",
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default().unwrap_or_default()
+    )
+}
+
+/// A random number in `[0, 1)`, from the randomly keyed hasher of the standard library.
+fn random_fraction() -> f64 {
+    let hash = RandomState::new().build_hasher().finish();
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
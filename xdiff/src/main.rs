@@ -1,3 +1,360 @@
-fn main() {
-    println!("Hello, world!");
+use std::{io, path::Path, process, time::SystemTime};
+
+use futures_util::{stream, StreamExt};
+use tokio::fs;
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, ValueEnum};
+use xdiff::{
+    cli::{
+        AcceptArgs, Action, Args, BenchArgs, MockArgs, ReqArgs, RunArgs, TuiArgs, ValidateArgs,
+        WatchArgs,
+    },
+    diff_text, hunks,
+    mock::{MockRoutes, Side},
+    tui::{App, TuiCase},
+    Baseline, Bench, CaseDetail, DiffArgs, DiffCase, DiffConfig, DiffProfile, Limiter, Outcome,
+    Redactor, Report, RequestProfile, RequestSummary, ResponseData, Snapshot, Validation, Watcher,
+};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    match args.action {
+        Action::Run(args) => run(args).await?,
+        Action::Accept(args) => accept(args).await?,
+        Action::Watch(args) => watch(args).await?,
+        Action::Tui(args) => tui(args).await?,
+        Action::Req(args) => req(args).await?,
+        Action::Bench(args) => bench(args).await?,
+        Action::Mock(args) => mock(args).await?,
+        Action::Validate(args) => validate(args).await?,
+        Action::Schema => print!("{}", DiffConfig::json_schema()?),
+    }
+
+    Ok(())
+}
+
+async fn run(mut args: RunArgs) -> Result<()> {
+    let config = load_config(&args.config).await?;
+    args.diff.limiter = Limiter::new(&config.limits)?;
+    let redactor = Redactor::new(&config.redact)?;
+    let names = match &args.profile {
+        Some(name) => vec![name.clone()],
+        None => config.profiles.keys().cloned().collect(),
+    };
+
+    let baseline = match &args.baseline {
+        Some(path) => Some(Baseline::load(path).await?),
+        None => None,
+    };
+
+    let cases = all_cases(&config, &names)?;
+    if args.record.is_some() || args.replay.is_some() {
+        Snapshot::check_names(cases.iter().map(|case| case.name.as_str()))?;
+    }
+    let results: Vec<_> = stream::iter(cases)
+        .map(|case| async {
+            let detail = run_case(&args, &case, &redactor).await;
+            (case, detail)
+        })
+        .buffered(config.limits.concurrency.unwrap_or(1).max(1))
+        .collect()
+        .await;
+
+    let mut report = Report::default();
+    for (case, detail) in results {
+        let options = args.diff.options(&case.profile.res.diff);
+        let (mut outcome, detail) = match detail {
+            Ok(detail) => (
+                Outcome::from_diff(diff_text(&detail.text1, &detail.text2, &options)),
+                Some(detail),
+            ),
+            Err(e) => (Outcome::from_diff(Err(e)), None),
+        };
+        let mut notes = Vec::new();
+        if let (Some(baseline), Some(detail), Outcome::Different { .. }) =
+            (&baseline, &detail, &outcome)
+        {
+            let hunks = hunks(&detail.text1, &detail.text2, options.algorithm);
+            let review = baseline.review(&case.name, &hunks, SystemTime::now())?;
+            if review.is_accepted() {
+                outcome = Outcome::Equal;
+            }
+            notes = review.notes;
+        }
+        report.push(case.name, redactor.redact_outcome(outcome), detail);
+        if let Some(case) = report.cases.last_mut() {
+            case.notes.extend(notes);
+        }
+    }
+    print!("{}", report.render(args.format)?);
+    if let Some(path) = &args.html {
+        fs::write(path, report.render_html()?).await?;
+    }
+    if !report.is_success() {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+async fn accept(mut args: AcceptArgs) -> Result<()> {
+    let config = load_config(&args.config).await?;
+    args.diff.limiter = Limiter::new(&config.limits)?;
+    let redactor = Redactor::new(&config.redact)?;
+    let names = match &args.profile {
+        Some(name) => vec![name.clone()],
+        None => config.profiles.keys().cloned().collect(),
+    };
+    let mut baseline = Baseline::load(&args.baseline).await?;
+    let cases = all_cases(&config, &names)?;
+    let results: Vec<_> = stream::iter(&cases)
+        .map(|case| async {
+            let (mut res1, mut res2) = case.profile.fetch(&args.diff).await?;
+            redactor.redact_response(&mut res1);
+            redactor.redact_response(&mut res2);
+            case.profile.filter_text(&res1, &res2)
+        })
+        .buffered(config.limits.concurrency.unwrap_or(1).max(1))
+        .collect()
+        .await;
+    for (case, texts) in cases.iter().zip(results) {
+        let (text1, text2) = texts?;
+        let options = args.diff.options(&case.profile.res.diff);
+        let hunks = hunks(&text1, &text2, options.algorithm);
+        eprintln!("{}: {} differences accepted", case.name, hunks.len());
+        baseline.accept(
+            &case.name,
+            hunks,
+            args.expires.as_deref(),
+            args.note.as_deref(),
+        );
+    }
+    baseline.save(&args.baseline).await
+}
+
+async fn run_case(args: &RunArgs, case: &DiffCase, redactor: &Redactor) -> Result<CaseDetail> {
+    let (mut res1, mut res2) = match &args.replay {
+        Some(dir) => {
+            let snapshot = Snapshot::load(dir, &case.name).await?;
+            (snapshot.req1, snapshot.req2)
+        }
+        None => case.profile.fetch(&args.diff).await?,
+    };
+    redactor.redact_response(&mut res1);
+    redactor.redact_response(&mut res2);
+    if let Some(dir) = &args.record {
+        Snapshot::new(res1.clone(), res2.clone())
+            .save(dir, &case.name)
+            .await?;
+    }
+    let (text1, text2) = case.profile.filter_text(&res1, &res2)?;
+    Ok(CaseDetail {
+        req1: summarize(&case.profile.req1, &res1, redactor),
+        req2: summarize(&case.profile.req2, &res2, redactor),
+        text1,
+        text2,
+        violations: case.profile.res.violations(&res1, &res2).await?,
+        options: args.diff.options(&case.profile.res.diff),
+    })
+}
+
+fn summarize(req: &RequestProfile, res: &ResponseData, redactor: &Redactor) -> RequestSummary {
+    RequestSummary {
+        method: req.method().to_string(),
+        url: redactor.redact_text(req.full_url().as_str()).into_owned(),
+        status: res.status.as_u16(),
+        version: format!("{:?}", res.version),
+        truncated: res.truncated,
+    }
+}
+
+async fn watch(mut args: WatchArgs) -> Result<()> {
+    let config = load_config(&args.config).await?;
+    args.diff.limiter = Limiter::new(&config.limits)?;
+    let profile = get_profile(&config, &args.profile)?;
+    let redactor = Redactor::new(&config.redact)?;
+    let cases = profile.cases(&args.profile)?;
+    let watcher = Watcher {
+        cases: &cases,
+        interval: args.interval,
+        log: args.log.clone(),
+        redactor: &redactor,
+        args: &args.diff,
+    };
+    watcher.run(&mut io::stdout()).await
+}
+
+async fn tui(mut args: TuiArgs) -> Result<()> {
+    let config = load_config(&args.config).await?;
+    args.diff.limiter = Limiter::new(&config.limits)?;
+    let redactor = Redactor::new(&config.redact)?;
+    let mut cases = Vec::new();
+    for (name, profile) in &config.profiles {
+        for case in profile.cases(name)? {
+            eprintln!("Fetching {}...", case.name);
+            let responses = match &args.replay {
+                Some(dir) => Snapshot::load(dir, &case.name)
+                    .await
+                    .map(|s| (s.req1, s.req2)),
+                None => case.profile.fetch(&args.diff).await,
+            };
+            let responses = responses
+                .map(|(mut res1, mut res2)| {
+                    redactor.redact_response(&mut res1);
+                    redactor.redact_response(&mut res2);
+                    (res1, res2)
+                })
+                .map_err(|e| redactor.redact_text(&format!("{:#}", e)).into_owned());
+            cases.push(TuiCase::new(name.clone(), case, responses, &args.diff));
+        }
+    }
+    App::new(config, args.config, cases, args.diff).run().await
+}
+
+async fn req(args: ReqArgs) -> Result<()> {
+    let config = load_config(&args.config).await?;
+    let profile = get_profile(&config, &args.profile)?;
+    let redactor = Redactor::new(&config.redact)?;
+    let diff = DiffArgs {
+        extra: args.extra.clone(),
+        limiter: Limiter::new(&config.limits)?,
+        ..Default::default()
+    };
+    let cases = profile.cases(&args.profile)?;
+    let mut responses = Vec::new();
+    for case in &cases {
+        let req = match args.side {
+            Side::Req1 => &case.profile.req1,
+            Side::Req2 => &case.profile.req2,
+        };
+        let mut res = req
+            .send_limited(&diff, case.profile.res.max_body_size)
+            .await?;
+        redactor.redact_response(&mut res);
+        if !args.json {
+            if cases.len() > 1 {
+                println!("{}", console::style(&case.name).bold());
+            }
+            if let Some(truncated) = res.truncated {
+                eprintln!(
+                    "{}",
+                    console::style(format!("Note: body truncated: {}", truncated)).yellow()
+                );
+            }
+            print!("{}", res.render_pretty()?);
+        }
+        let mut value = res.to_json();
+        value["name"] = case.name.clone().into();
+        responses.push(value);
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&responses)?);
+    }
+    Ok(())
+}
+
+async fn bench(mut args: BenchArgs) -> Result<()> {
+    let config = load_config(&args.config).await?;
+    args.diff.limiter = Limiter::new(&config.limits)?;
+    let profile = get_profile(&config, &args.profile)?;
+    let mut reports = Vec::new();
+    for case in profile.cases(&args.profile)? {
+        eprintln!(
+            "Sending {} requests to each side of {}...",
+            args.requests, case.name
+        );
+        let bench = Bench {
+            case: &case,
+            requests: args.requests,
+            concurrency: args.concurrency,
+            args: &args.diff,
+        };
+        reports.push(bench.run().await?);
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for report in &reports {
+            print!("{}", report.render()?);
+        }
+    }
+    Ok(())
+}
+
+async fn mock(args: MockArgs) -> Result<()> {
+    let config = load_config(&args.config).await?;
+    let server = MockRoutes::from_snapshots(&config, &args.dir, args.side)
+        .await?
+        .serve(args.addr)
+        .await?;
+    let side = args.side.to_possible_value().map(|v| v.get_name());
+    eprintln!(
+        "Serving the {} responses from {} on {}",
+        side.unwrap_or_default(),
+        args.dir.display(),
+        server.url()
+    );
+    server.wait().await
+}
+
+async fn validate(args: ValidateArgs) -> Result<()> {
+    let content = fs::read_to_string(&args.config)
+        .await
+        .with_context(|| format!("failed to read {}", args.config.display()))?;
+    let mut validation = Validation::new(&args.config, &content);
+    if validation.is_valid() && (args.fetch || args.replay.is_some()) {
+        let config = load_config(&args.config).await?;
+        let diff = DiffArgs {
+            limiter: Limiter::new(&config.limits)?,
+            ..Default::default()
+        };
+        for (name, profile) in &config.profiles {
+            let mut responses = Vec::new();
+            for case in profile.cases(name)? {
+                let result = match &args.replay {
+                    Some(dir) => Snapshot::load(dir, &case.name)
+                        .await
+                        .map(|s| (s.req1, s.req2)),
+                    None => case.profile.fetch(&diff).await,
+                };
+                match result {
+                    Ok(r) => responses.push(r),
+                    Err(e) => {
+                        validation.report(name, format!("no responses for {}: {:#}", case.name, e))
+                    }
+                }
+            }
+            validation.check_skip_rules(name, &profile.res, &responses)?;
+        }
+    }
+
+    let problems = validation.into_problems();
+    if problems.is_empty() {
+        println!("{} is valid.", args.config.display());
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("{}:{}", args.config.display(), problem);
+    }
+    process::exit(1);
+}
+
+async fn load_config(path: &Path) -> Result<DiffConfig> {
+    DiffConfig::load(path).await
+}
+
+/// The cases of the named profiles, in order.
+fn all_cases(config: &DiffConfig, names: &[String]) -> Result<Vec<DiffCase>> {
+    let mut cases = Vec::new();
+    for name in names {
+        cases.extend(get_profile(config, name)?.cases(name)?);
+    }
+    Ok(cases)
+}
+
+fn get_profile<'a>(config: &'a DiffConfig, name: &str) -> Result<&'a DiffProfile> {
+    config
+        .get_profile(name)
+        .ok_or_else(|| anyhow!("Profile {} not found in config file", name))
 }
@@ -0,0 +1,161 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Variables substituted as `{{name}}` into both requests. Every combination of the listed
+/// values (and of the CSV rows, if any) becomes its own case.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct MatrixProfile {
+    /// A CSV file with a header row; each row is one combination of its columns. A relative
+    /// path is relative to the config file.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub csv: Option<PathBuf>,
+    #[serde(flatten)]
+    pub vars: BTreeMap<String, MatrixValues>,
+    /// The directory of the config file, set when it's loaded.
+    #[serde(skip)]
+    pub base: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(untagged)]
+pub enum MatrixValues {
+    List(Vec<Value>),
+    /// Integers from `start` to `end`, both inclusive.
+    Range {
+        start: i64,
+        end: i64,
+        #[serde(default = "default_step")]
+        step: i64,
+    },
+}
+
+fn default_step() -> i64 {
+    1
+}
+
+pub type Vars = BTreeMap<String, Value>;
+
+impl MatrixProfile {
+    /// All combinations, in a stable order. A matrix without any is an error, since its
+    /// profile would pass without checking anything.
+    pub fn combinations(&self) -> Result<Vec<Vars>> {
+        let mut combos = match &self.csv {
+            Some(path) => {
+                let rows = match &self.base {
+                    Some(base) => read_csv(&base.join(path))?,
+                    None => read_csv(path)?,
+                };
+                if rows.is_empty() {
+                    bail!("matrix csv {} has no rows", path.display());
+                }
+                rows
+            }
+            None => vec![Vars::new()],
+        };
+        for (name, values) in &self.vars {
+            let values = values.values()?;
+            if values.is_empty() {
+                bail!("matrix variable `{}` has no values", name);
+            }
+            combos = combos
+                .into_iter()
+                .flat_map(|combo| {
+                    values.iter().map(move |v| {
+                        let mut combo = combo.clone();
+                        combo.insert(name.clone(), v.clone());
+                        combo
+                    })
+                })
+                .collect();
+        }
+        Ok(combos)
+    }
+}
+
+impl MatrixValues {
+    fn values(&self) -> Result<Vec<Value>> {
+        match self {
+            Self::List(values) => Ok(values.clone()),
+            Self::Range { start, end, step } => {
+                if *step <= 0 {
+                    bail!("matrix range step must be positive, got {}", step);
+                }
+                Ok((*start..=*end)
+                    .step_by(*step as usize)
+                    .map(Value::from)
+                    .collect())
+            }
+        }
+    }
+}
+
+fn read_csv(path: &PathBuf) -> Result<Vec<Vars>> {
+    let mut reader = csv::Reader::from_path(path)
+        .with_context(|| format!("failed to open matrix csv {}", path.display()))?;
+    let headers = reader.headers()?.clone();
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let row = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+            .collect();
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// A name suffix like `[id=1,locale=en]` identifying one combination.
+pub fn case_suffix(vars: &Vars) -> String {
+    let parts: Vec<_> = vars
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, display_value(v)))
+        .collect();
+    format!("[{}]", parts.join(","))
+}
+
+/// Replace `{{name}}` placeholders in every string of `value`. A string that is exactly one
+/// placeholder takes the variable's JSON value, so numbers stay numbers.
+pub fn interpolate(value: &mut Value, vars: &Vars) {
+    match value {
+        Value::String(s) => {
+            if let Some(v) = s
+                .strip_prefix("{{")
+                .and_then(|s| s.strip_suffix("}}"))
+                .and_then(|name| vars.get(name.trim()))
+            {
+                *value = v.clone();
+            } else {
+                *s = interpolate_str(s, vars);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| interpolate(v, vars)),
+        Value::Object(map) => map.values_mut().for_each(|v| interpolate(v, vars)),
+        _ => {}
+    }
+}
+
+/// Replace `{{name}}` placeholders in `s`, including their percent-encoded form as found in
+/// parsed URLs.
+pub fn interpolate_str(s: &str, vars: &Vars) -> String {
+    let mut s = s.to_string();
+    for (name, v) in vars {
+        let v = display_value(v);
+        s = s
+            .replace(&format!("{{{{{}}}}}", name), &v)
+            .replace(&format!("%7B%7B{}%7D%7D", name), &v);
+    }
+    s
+}
+
+fn display_value(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Result};
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{
+        HeaderName as AxumHeaderName, HeaderValue as AxumHeaderValue, StatusCode as AxumStatus,
+    },
+    response::Response,
+    Router,
+};
+use reqwest::{
+    header::{self, HeaderMap, HeaderValue},
+    Method, StatusCode, Version,
+};
+use serde_json::{json, Value};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use crate::{req::header_map, DiffConfig, ResponseData, Snapshot};
+
+/// Scripted responses for a [`MockServer`], by method and path. A route given several
+/// responses answers with them in turn, starting over after the last one.
+#[derive(Debug, Clone, Default)]
+pub struct MockRoutes {
+    routes: Vec<Route>,
+}
+
+#[derive(Debug, Clone)]
+struct Route {
+    method: Method,
+    path: String,
+    /// Only match this exact query, or any query when not set.
+    query: Option<String>,
+    responses: Vec<ResponseData>,
+}
+
+/// A request received by a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    pub method: Method,
+    /// The path with its query, if any.
+    pub uri: String,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+/// A local HTTP server answering with scripted responses, as a fake upstream for tests and
+/// for `xdiff mock`. It stops when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    received: Arc<Mutex<Vec<ReceivedRequest>>>,
+    handle: JoinHandle<()>,
+}
+
+struct Shared {
+    routes: Vec<Route>,
+    calls: Mutex<HashMap<usize, usize>>,
+    received: Arc<Mutex<Vec<ReceivedRequest>>>,
+}
+
+/// A `200 OK` response with a JSON body.
+pub fn json(body: Value) -> ResponseData {
+    text(StatusCode::OK, body.to_string()).with_header(header::CONTENT_TYPE, "application/json")
+}
+
+/// A response with a plain text body.
+pub fn text(status: StatusCode, body: impl Into<String>) -> ResponseData {
+    ResponseData {
+        status,
+        version: Version::HTTP_11,
+        headers: HeaderMap::new(),
+        body: body.into(),
+        truncated: None,
+    }
+}
+
+/// Builders for scripting mock responses.
+impl ResponseData {
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_header(mut self, name: header::HeaderName, value: &'static str) -> Self {
+        self.headers.append(name, HeaderValue::from_static(value));
+        self
+    }
+}
+
+impl MockRoutes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer `method` requests to `path` with `response`. A `path` with a query only matches
+    /// requests with exactly that query, and is preferred over the same path without one.
+    /// Calling this again for the same route adds to its sequence of responses.
+    pub fn respond(mut self, method: Method, path: &str, response: ResponseData) -> Self {
+        let (path, query) = match path.split_once('?') {
+            Some((path, query)) => (path.to_string(), Some(query.to_string())),
+            None => (path.to_string(), None),
+        };
+        match self
+            .routes
+            .iter_mut()
+            .find(|r| r.method == method && r.path == path && r.query == query)
+        {
+            Some(route) => route.responses.push(response),
+            None => self.routes.push(Route {
+                method,
+                path,
+                query,
+                responses: vec![response],
+            }),
+        }
+        self
+    }
+
+    /// Answer the requests of one side of the cases in `config` with their snapshot in `dir`,
+    /// as recorded by `xdiff run --record`. Cases without a snapshot are left out.
+    pub async fn from_snapshots(config: &DiffConfig, dir: &Path, side: Side) -> Result<Self> {
+        let mut routes = Self::new();
+        for (name, profile) in &config.profiles {
+            for case in profile.cases(name)? {
+                if !Snapshot::path(dir, &case.name).exists() {
+                    continue;
+                }
+                let snapshot = Snapshot::load(dir, &case.name).await?;
+                let (req, res) = match side {
+                    Side::Req1 => (&case.profile.req1, snapshot.req1),
+                    Side::Req2 => (&case.profile.req2, snapshot.req2),
+                };
+                let url = req.full_url();
+                let path = match url.query() {
+                    Some(query) => format!("{}?{}", url.path(), query),
+                    None => url.path().to_string(),
+                };
+                routes = routes.respond(req.method(), &path, res);
+            }
+        }
+        if routes.routes.is_empty() {
+            bail!(
+                "no snapshots for the profiles of this config in {}",
+                dir.display()
+            );
+        }
+        Ok(routes)
+    }
+
+    /// Serve on an ephemeral port of localhost.
+    pub async fn start(self) -> Result<MockServer> {
+        self.serve("127.0.0.1:0".parse()?).await
+    }
+
+    pub async fn serve(self, addr: SocketAddr) -> Result<MockServer> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let shared = Arc::new(Shared {
+            routes: self.routes,
+            calls: Mutex::new(HashMap::new()),
+            received: received.clone(),
+        });
+        let app = Router::new().fallback(handle).with_state(shared);
+        let handle = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Ok(MockServer {
+            addr,
+            received,
+            handle,
+        })
+    }
+}
+
+/// Which side of the cases a mock server stands in for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Side {
+    Req1,
+    Req2,
+}
+
+impl MockServer {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The base url of the server, without a trailing slash.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// The requests received so far, in order.
+    pub fn received(&self) -> Vec<ReceivedRequest> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Serve until interrupted with ctrl-c.
+    pub async fn wait(self) -> Result<()> {
+        tokio::signal::ctrl_c().await?;
+        Ok(())
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn handle(State(shared): State<Arc<Shared>>, req: Request) -> Response {
+    let (parts, body) = req.into_parts();
+    let method = Method::from_bytes(parts.method.as_str().as_bytes()).unwrap_or_default();
+    let (path, query) = (parts.uri.path(), parts.uri.query());
+    let body = to_bytes(body, usize::MAX).await.unwrap_or_default();
+    let headers = header_map(
+        parts
+            .headers
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_bytes())),
+    )
+    .unwrap_or_default();
+    shared.received.lock().unwrap().push(ReceivedRequest {
+        method: method.clone(),
+        uri: parts.uri.to_string(),
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    });
+
+    let candidates = || {
+        shared
+            .routes
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.method == method && r.path == path)
+    };
+    let route = candidates()
+        .find(|(_, r)| r.query.is_some() && r.query.as_deref() == query)
+        .or_else(|| candidates().find(|(_, r)| r.query.is_none()));
+    let res = match route {
+        Some((idx, route)) => {
+            let mut calls = shared.calls.lock().unwrap();
+            let call = calls.entry(idx).or_default();
+            let res = route.responses[*call % route.responses.len()].clone();
+            *call += 1;
+            res
+        }
+        None => json(json!({ "error": format!("no mock response for {} {}", method, parts.uri) }))
+            .with_status(StatusCode::NOT_FOUND),
+    };
+    to_response(res)
+}
+
+fn to_response(res: ResponseData) -> Response {
+    let mut response = Response::new(Body::from(res.body));
+    *response.status_mut() =
+        AxumStatus::from_u16(res.status.as_u16()).unwrap_or(AxumStatus::INTERNAL_SERVER_ERROR);
+    for (name, value) in &res.headers {
+        // The framing of the recorded response doesn't apply to the body served now.
+        if [
+            header::CONTENT_LENGTH,
+            header::TRANSFER_ENCODING,
+            header::CONNECTION,
+        ]
+        .contains(name)
+        {
+            continue;
+        }
+        if let (Ok(name), Ok(value)) = (
+            AxumHeaderName::from_bytes(name.as_str().as_bytes()),
+            AxumHeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            response.headers_mut().append(name, value);
+        }
+    }
+    response
+}
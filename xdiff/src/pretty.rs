@@ -0,0 +1,76 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use console::style;
+use serde_json::Value;
+
+use crate::ResponseData;
+
+impl ResponseData {
+    /// The status line, headers and body for reading in a terminal, with JSON bodies
+    /// pretty-printed and highlighted.
+    pub fn render_pretty(&self) -> Result<String> {
+        let mut output = String::new();
+        let status = style(format!("{:?} {}", self.version, self.status)).bold();
+        let status = match self.status.as_u16() {
+            200..=299 => status.green(),
+            300..=399 => status.cyan(),
+            400..=499 => status.yellow(),
+            _ => status.red(),
+        };
+        writeln!(output, "{}", status)?;
+        for (name, value) in &self.headers {
+            writeln!(
+                output,
+                "{}: {}",
+                style(name).cyan(),
+                String::from_utf8_lossy(value.as_bytes())
+            )?;
+        }
+        writeln!(output)?;
+        match serde_json::from_str::<Value>(&self.body) {
+            Ok(body) if self.is_json() => {
+                write_json(&mut output, &body, 0)?;
+                writeln!(output)?;
+            }
+            _ => writeln!(output, "{}", self.body)?,
+        }
+        Ok(output)
+    }
+}
+
+/// Write `value` like `serde_json::to_string_pretty`, with keys and scalars colored by type.
+fn write_json(output: &mut String, value: &Value, indent: usize) -> Result<()> {
+    let pad = |n: usize| " ".repeat(n);
+    match value {
+        Value::Null | Value::Bool(_) => write!(output, "{}", style(value).magenta())?,
+        Value::Number(_) => write!(output, "{}", style(value).yellow())?,
+        Value::String(_) => write!(output, "{}", style(value).green())?,
+        Value::Array(items) if items.is_empty() => write!(output, "[]")?,
+        Value::Object(map) if map.is_empty() => write!(output, "{{}}")?,
+        Value::Array(items) => {
+            writeln!(output, "[")?;
+            for (idx, item) in items.iter().enumerate() {
+                write!(output, "{}", pad(indent + 2))?;
+                write_json(output, item, indent + 2)?;
+                writeln!(output, "{}", if idx + 1 < items.len() { "," } else { "" })?;
+            }
+            write!(output, "{}]", pad(indent))?;
+        }
+        Value::Object(map) => {
+            writeln!(output, "{{")?;
+            for (idx, (key, item)) in map.iter().enumerate() {
+                write!(
+                    output,
+                    "{}{}: ",
+                    pad(indent + 2),
+                    style(Value::from(key.as_str())).blue().bold()
+                )?;
+                write_json(output, item, indent + 2)?;
+                writeln!(output, "{}", if idx + 1 < map.len() { "," } else { "" })?;
+            }
+            write!(output, "{}}}", pad(indent))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use std::borrow::Cow;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderValue};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Outcome, ResponseData};
+
+/// Headers whose values are always redacted.
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+    "api-key",
+    "x-auth-token",
+    "x-access-token",
+    "x-csrf-token",
+    "x-amz-security-token",
+];
+
+const REDACTED: &str = "[REDACTED]";
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct RedactConfig {
+    /// Extra header names to redact, on top of the built-in sensitive ones.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub headers: Vec<String>,
+    /// Regexes whose matches are redacted from header values and bodies.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub patterns: Vec<String>,
+}
+
+/// Masks secrets before anything is printed, reported or recorded.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    headers: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl RedactConfig {
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.patterns.is_empty()
+    }
+}
+
+impl Redactor {
+    pub fn new(config: &RedactConfig) -> Result<Self> {
+        let headers = SENSITIVE_HEADERS
+            .iter()
+            .map(|h| h.to_string())
+            .chain(config.headers.iter().map(|h| h.to_ascii_lowercase()))
+            .collect();
+        let patterns = config
+            .patterns
+            .iter()
+            .map(|p| Regex::new(p).with_context(|| format!("invalid redact pattern `{}`", p)))
+            .collect::<Result<_>>()?;
+        Ok(Self { headers, patterns })
+    }
+
+    pub fn redact_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for re in &self.patterns {
+            if let Cow::Owned(s) = re.replace_all(&text, REDACTED) {
+                text = Cow::Owned(s);
+            }
+        }
+        text
+    }
+
+    pub fn redact_headers(&self, headers: &mut HeaderMap) {
+        for (name, value) in headers.iter_mut() {
+            if self.headers.iter().any(|h| h == name.as_str()) {
+                *value = HeaderValue::from_static(REDACTED);
+            } else if let Ok(s) = value.to_str() {
+                if let Cow::Owned(s) = self.redact_text(s) {
+                    if let Ok(v) = HeaderValue::from_str(&s) {
+                        *value = v;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn redact_response(&self, res: &mut ResponseData) {
+        self.redact_headers(&mut res.headers);
+        if res.is_json() {
+            if let Ok(mut value) = serde_json::from_str::<Value>(&res.body) {
+                if self.redact_json(&mut value) {
+                    res.body = value.to_string();
+                }
+            }
+        }
+        if let Cow::Owned(body) = self.redact_text(&res.body) {
+            res.body = body;
+        }
+    }
+
+    /// Mask JSON fields named like a sensitive header, e.g. an echoed `authorization`.
+    fn redact_json(&self, value: &mut Value) -> bool {
+        let mut redacted = false;
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map.iter_mut() {
+                    if self.headers.iter().any(|h| h.eq_ignore_ascii_case(k)) {
+                        *v = Value::String(REDACTED.to_string());
+                        redacted = true;
+                    } else {
+                        redacted |= self.redact_json(v);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for v in items {
+                    redacted |= self.redact_json(v);
+                }
+            }
+            _ => {}
+        }
+        redacted
+    }
+
+    /// Error messages may embed URLs or payloads, so they go through the patterns too.
+    pub fn redact_outcome(&self, outcome: Outcome) -> Outcome {
+        match outcome {
+            Outcome::Error { error } => Outcome::Error {
+                error: self.redact_text(&error).into_owned(),
+            },
+            outcome => outcome,
+        }
+    }
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::new(&RedactConfig::default()).expect("built-in redaction rules are valid")
+    }
+}
@@ -0,0 +1,264 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{utils::escape_xml, DiffOptions, Truncated};
+
+/// The outcome of diffing one case.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome {
+    Equal,
+    Different { diff: String },
+    Error { error: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseResult {
+    pub name: String,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    /// Caveats about what was compared, e.g. a truncated body.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Requirements of the profile that a response doesn't meet, which fail the case even if
+    /// the responses are equal.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<String>,
+    #[serde(skip)]
+    pub detail: Option<CaseDetail>,
+}
+
+/// What was compared in a case, for reports that show more than the diff itself.
+#[derive(Debug, Clone)]
+pub struct CaseDetail {
+    pub req1: RequestSummary,
+    pub req2: RequestSummary,
+    /// The filtered text of each response that the diff was computed from.
+    pub text1: String,
+    pub text2: String,
+    pub violations: Vec<String>,
+    /// How the texts were diffed, so that every report shows the same differences.
+    pub options: DiffOptions,
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestSummary {
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    /// The negotiated protocol version, e.g. `HTTP/2.0`.
+    pub version: String,
+    pub truncated: Option<Truncated>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub cases: Vec<CaseResult>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Terminal,
+    Json,
+    Junit,
+}
+
+impl Outcome {
+    pub fn from_diff(diff: Result<String>) -> Self {
+        match diff {
+            Ok(diff) if diff.is_empty() => Self::Equal,
+            Ok(diff) => Self::Different { diff },
+            Err(e) => Self::Error {
+                error: format!("{:#}", e),
+            },
+        }
+    }
+
+    pub fn is_equal(&self) -> bool {
+        matches!(self, Self::Equal)
+    }
+
+    /// The same outcome with terminal colors stripped from the diff.
+    pub fn plain(&self) -> Self {
+        match self {
+            Self::Different { diff } => Self::Different {
+                diff: console::strip_ansi_codes(diff).into_owned(),
+            },
+            outcome => outcome.clone(),
+        }
+    }
+}
+
+impl CaseDetail {
+    /// Caveats about what was compared: bodies that were not compared in full, and protocol
+    /// versions that differ.
+    pub fn notes(&self) -> Vec<String> {
+        let mut notes: Vec<String> = [("req1", &self.req1), ("req2", &self.req2)]
+            .into_iter()
+            .filter_map(|(side, req)| {
+                req.truncated
+                    .map(|t| format!("{} body truncated: {}", side, t))
+            })
+            .collect();
+        if self.req1.version != self.req2.version {
+            notes.push(format!(
+                "protocol versions differ: req1 {}, req2 {}",
+                self.req1.version, self.req2.version
+            ));
+        }
+        notes
+    }
+}
+
+impl Report {
+    pub fn push(&mut self, name: impl Into<String>, outcome: Outcome, detail: Option<CaseDetail>) {
+        let notes = detail.as_ref().map(CaseDetail::notes).unwrap_or_default();
+        let violations = detail
+            .as_ref()
+            .map(|d| d.violations.clone())
+            .unwrap_or_default();
+        self.cases.push(CaseResult {
+            name: name.into(),
+            outcome,
+            notes,
+            violations,
+            detail,
+        });
+    }
+
+    pub fn count(&self, f: impl Fn(&Outcome) -> bool) -> usize {
+        self.cases.iter().filter(|c| f(&c.outcome)).count()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.cases
+            .iter()
+            .all(|c| c.outcome.is_equal() && c.violations.is_empty())
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(|o| matches!(o, Outcome::Error { .. })) > 0
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Terminal => self.render_terminal(),
+            OutputFormat::Json => self.render_json(),
+            OutputFormat::Junit => self.render_junit(),
+        }
+    }
+
+    fn render_terminal(&self) -> Result<String> {
+        let mut output = String::new();
+        let single = self.cases.len() == 1;
+        for case in &self.cases {
+            if !single {
+                writeln!(&mut output, "{}", console::style(&case.name).bold())?;
+            }
+            for note in &case.notes {
+                writeln!(
+                    &mut output,
+                    "{}",
+                    console::style(format!("Note: {}", note)).yellow()
+                )?;
+            }
+            for violation in &case.violations {
+                writeln!(
+                    &mut output,
+                    "{}",
+                    console::style(format!("Violation: {}", violation)).red()
+                )?;
+            }
+            match &case.outcome {
+                Outcome::Equal => writeln!(&mut output, "No differences found.")?,
+                Outcome::Different { diff } => write!(&mut output, "{}", diff)?,
+                Outcome::Error { error } => writeln!(&mut output, "Error: {}", error)?,
+            }
+        }
+        Ok(output)
+    }
+
+    fn render_json(&self) -> Result<String> {
+        let cases: Vec<_> = self
+            .cases
+            .iter()
+            .map(|c| CaseResult {
+                name: c.name.clone(),
+                outcome: c.outcome.plain(),
+                notes: c.notes.clone(),
+                violations: c.violations.clone(),
+                detail: None,
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(&Report { cases })? + "\n")
+    }
+
+    fn render_junit(&self) -> Result<String> {
+        let failures = self
+            .cases
+            .iter()
+            .filter(|c| match c.outcome {
+                Outcome::Equal => !c.violations.is_empty(),
+                Outcome::Different { .. } => true,
+                Outcome::Error { .. } => false,
+            })
+            .count();
+        let errors = self.count(|o| matches!(o, Outcome::Error { .. }));
+
+        let mut output = String::new();
+        writeln!(&mut output, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            &mut output,
+            r#"<testsuite name="xdiff" tests="{}" failures="{}" errors="{}">"#,
+            self.cases.len(),
+            failures,
+            errors
+        )?;
+        for case in &self.cases {
+            let name = escape_xml(&case.name);
+            let notes = if case.notes.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    "<system-out>{}</system-out>",
+                    escape_xml(&case.notes.join("\n"))
+                )
+            };
+            let violations: String = case.violations.iter().map(|v| v.clone() + "\n").collect();
+            let failure = |message: &str, text: String| {
+                format!(
+                    r#"<failure message="{}">{}</failure>"#,
+                    message,
+                    escape_xml(&text)
+                )
+            };
+            let outcome = match case.outcome.plain() {
+                Outcome::Equal if violations.is_empty() => String::new(),
+                Outcome::Equal => failure("requirements not met", violations),
+                Outcome::Different { diff } => {
+                    failure("responses differ", violations + diff.as_str())
+                }
+                Outcome::Error { error } => format!(r#"<error message="{}"/>"#, escape_xml(&error)),
+            };
+            let body = outcome + notes.as_str();
+            if body.is_empty() {
+                writeln!(
+                    &mut output,
+                    r#"  <testcase classname="xdiff" name="{}"/>"#,
+                    name
+                )?;
+            } else {
+                writeln!(
+                    &mut output,
+                    r#"  <testcase classname="xdiff" name="{}">{}</testcase>"#,
+                    name, body
+                )?;
+            }
+        }
+        writeln!(&mut output, "</testsuite>")?;
+        Ok(output)
+    }
+}
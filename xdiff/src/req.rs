@@ -0,0 +1,511 @@
+use std::{
+    borrow::Cow,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    Client, Method, Response, StatusCode, Url, Version,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::fs;
+
+use crate::{
+    grpc::GrpcProfile,
+    matrix::{interpolate, interpolate_str, Vars},
+    script::ScriptProfile,
+    signing::SigningProfile,
+    websocket::WebsocketProfile,
+    DiffArgs,
+};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct RequestProfile {
+    #[serde(with = "http_serde::method", default)]
+    #[schemars(with = "String")]
+    pub method: Method,
+    #[schemars(with = "String")]
+    pub url: Url,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub params: Option<Value>,
+    #[serde(
+        skip_serializing_if = "HeaderMap::is_empty",
+        with = "http_serde::header_map",
+        default
+    )]
+    #[schemars(with = "std::collections::BTreeMap<String, String>")]
+    pub headers: HeaderMap,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub body: Option<Value>,
+    /// Send a GraphQL operation instead of `body`, as a POST with the standard JSON envelope.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub graphql: Option<GraphqlProfile>,
+    /// Make a gRPC call to `url` instead, with `headers` sent as metadata.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub grpc: Option<GrpcProfile>,
+    /// Hold a WebSocket conversation with `url` instead, and diff its transcript.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub websocket: Option<WebsocketProfile>,
+    /// Rhai scripts rewriting the request before it is sent and the response after.
+    #[serde(skip_serializing_if = "ScriptProfile::is_empty", default)]
+    pub script: ScriptProfile,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub signing: Option<SigningProfile>,
+    /// The HTTP version to use, negotiated by default.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub http_version: Option<HttpVersion>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HttpVersion {
+    /// HTTP/1.1 only.
+    Http1,
+    /// HTTP/2 as negotiated over TLS. The request fails if the server answers with another
+    /// version, which is what happens over plain text.
+    Http2,
+    /// HTTP/2 without negotiating, which also works over plain text (h2c).
+    Http2PriorKnowledge,
+}
+
+/// A request field set from the command line for both sides: `key=value` sets a query
+/// parameter, `%key=value` a header and `@key=value` a field of the JSON body, where the value
+/// is parsed as JSON if it can be.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Override {
+    Query(String, String),
+    Header(String, String),
+    Body(String, Value),
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct GraphqlProfile {
+    /// The query document, inline.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub query: Option<String>,
+    /// A file to read the query document from, instead of `query`. A relative path is
+    /// relative to the config file.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub query_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub variables: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub operation_name: Option<String>,
+    /// The directory of the config file, set when it's loaded.
+    #[serde(skip)]
+    pub base: Option<PathBuf>,
+}
+
+/// What we keep of a response once it has been fully read. This is also what gets recorded
+/// to, and replayed from, a snapshot directory.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResponseData {
+    #[serde(with = "http_serde::status_code")]
+    pub status: StatusCode,
+    #[serde(with = "http_serde::version")]
+    pub version: Version,
+    #[serde(with = "http_serde::header_map")]
+    pub headers: HeaderMap,
+    pub body: String,
+    /// Set when only part of the body was kept.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub truncated: Option<Truncated>,
+}
+
+/// How much of a body was read and kept, for bodies cut at `max_body_size` or streamed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct Truncated {
+    /// Bytes read from the server.
+    pub read: u64,
+    /// Bytes kept in the body.
+    pub kept: u64,
+    /// Whether reading stopped at `max_body_size` before the end of the body.
+    pub limited: bool,
+    /// Differing regions of a streamed body that were counted but not kept.
+    #[serde(skip_serializing_if = "is_zero", default)]
+    pub hidden_regions: usize,
+}
+
+impl RequestProfile {
+    pub async fn send(&self, args: &DiffArgs) -> Result<ResponseData> {
+        self.send_limited(args, None).await
+    }
+
+    /// Like [`send`](Self::send), but reading at most `limit` bytes of an HTTP body.
+    pub async fn send_limited(&self, args: &DiffArgs, limit: Option<u64>) -> Result<ResponseData> {
+        let req = self.prepare(args)?;
+        let client = req.client()?;
+        let permit = args.limiter.acquire(&req.url).await;
+        let res = req.exchange(&client, limit).await?;
+        drop(permit);
+        self.script.after(res)
+    }
+
+    /// Like [`send`](Self::send), but over `client` so that its connections are reused, and
+    /// with the time the exchange took, not counting the wait for the limiter.
+    pub(crate) async fn send_timed(
+        &self,
+        args: &DiffArgs,
+        client: &Client,
+    ) -> (Duration, Result<ResponseData>) {
+        let req = match self.prepare(args) {
+            Ok(req) => req,
+            Err(e) => return (Duration::ZERO, Err(e)),
+        };
+        let _permit = args.limiter.acquire(&req.url).await;
+        let start = Instant::now();
+        let res = req.exchange(client, None).await;
+        let elapsed = start.elapsed();
+        (elapsed, res.and_then(|res| self.script.after(res)))
+    }
+
+    /// Send an HTTP request, returning the response before its body is read.
+    pub(crate) async fn request(&self, args: &DiffArgs) -> Result<Response> {
+        let req = self.prepare(args)?;
+        req.http(&req.client()?).await
+    }
+
+    /// An HTTP client speaking the configured protocol version.
+    pub(crate) fn client(&self) -> Result<Client> {
+        let builder = match self.http_version {
+            Some(HttpVersion::Http1) => Client::builder().http1_only(),
+            Some(HttpVersion::Http2PriorKnowledge) => Client::builder().http2_prior_knowledge(),
+            Some(HttpVersion::Http2) | None => Client::builder(),
+        };
+        Ok(builder.build()?)
+    }
+
+    /// The request to send: command line overrides are applied first, then the `pre` script.
+    /// Signing comes last, once the body is serialized.
+    fn prepare(&self, args: &DiffArgs) -> Result<Cow<'_, Self>> {
+        let mut req = Cow::Borrowed(self);
+        if !args.extra.is_empty() {
+            req = Cow::Owned(req.with_overrides(&args.extra)?);
+        }
+        if let Some(scripted) = req.script.before(&req)? {
+            req = Cow::Owned(scripted);
+        }
+        Ok(req)
+    }
+
+    fn with_overrides(&self, overrides: &[Override]) -> Result<Self> {
+        let mut req = self.clone();
+        for o in overrides {
+            match o {
+                Override::Query(key, value) => {
+                    let params = req.params.get_or_insert_with(|| json!({}));
+                    match params {
+                        Value::Object(map) => map.insert(key.clone(), json!(value)),
+                        _ => bail!("can't override {}: params is not a map", key),
+                    };
+                }
+                Override::Header(key, value) => {
+                    req.headers.insert(
+                        HeaderName::from_bytes(key.as_bytes())?,
+                        HeaderValue::from_str(value)?,
+                    );
+                }
+                Override::Body(key, value) => {
+                    match req.body.get_or_insert_with(|| json!({})) {
+                        Value::Object(map) => map.insert(key.clone(), value.clone()),
+                        _ => bail!("can't override {}: body is not a JSON object", key),
+                    };
+                }
+            }
+        }
+        Ok(req)
+    }
+
+    async fn exchange(&self, client: &Client, limit: Option<u64>) -> Result<ResponseData> {
+        if self.signing.is_some() && (self.grpc.is_some() || self.websocket.is_some()) {
+            bail!("only HTTP requests can be signed");
+        }
+        if let Some(grpc) = &self.grpc {
+            return grpc.send(&self.url, &self.headers).await;
+        }
+        if let Some(websocket) = &self.websocket {
+            return websocket.send(&self.full_url(), &self.headers).await;
+        }
+        let res = self.http(client).await?;
+        match limit {
+            Some(limit) => ResponseData::read_limited(res, limit).await,
+            None => Ok(ResponseData {
+                status: res.status(),
+                version: res.version(),
+                headers: res.headers().clone(),
+                body: res.text().await?,
+                truncated: None,
+            }),
+        }
+    }
+
+    async fn http(&self, client: &Client) -> Result<Response> {
+        if self.grpc.is_some() || self.websocket.is_some() {
+            bail!("only HTTP responses can be streamed");
+        }
+        let body = match &self.graphql {
+            Some(graphql) => Some(graphql.envelope().await?),
+            None => self.body.clone(),
+        };
+        let mut req = client
+            .request(self.method(), self.full_url())
+            .headers(self.headers.clone());
+        if let Some(body) = &body {
+            if !self.headers.contains_key(header::CONTENT_TYPE) {
+                req = req.header(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/json"),
+                );
+            }
+            req = req.body(serde_json::to_string(body)?);
+        }
+        let mut req = req.build()?;
+        if let Some(signing) = &self.signing {
+            signing.sign(&mut req, SystemTime::now())?;
+        }
+
+        let res = client.execute(req).await?;
+        if self.http_version == Some(HttpVersion::Http2) && res.version() != Version::HTTP_2 {
+            bail!("expected HTTP/2 from {}, got {:?}", self.url, res.version());
+        }
+        Ok(res)
+    }
+
+    /// The method actually sent: always POST for GraphQL and gRPC requests.
+    pub fn method(&self) -> Method {
+        if self.graphql.is_some() || self.grpc.is_some() {
+            return Method::POST;
+        }
+        self.method.clone()
+    }
+
+    /// The url with `params` appended to its query.
+    pub fn full_url(&self) -> Url {
+        let mut url = self.url.clone();
+        if let Some(params) = &self.params {
+            let pairs = query_pairs(params);
+            if !pairs.is_empty() {
+                url.query_pairs_mut().extend_pairs(pairs);
+            }
+        }
+        url
+    }
+
+    /// A copy of this profile with `{{name}}` placeholders replaced by `vars`.
+    pub fn interpolate(&self, vars: &Vars) -> Result<Self> {
+        let mut profile = self.clone();
+        profile.url = interpolate_str(self.url.as_str(), vars).parse()?;
+        for value in profile.headers.values_mut() {
+            if let Ok(s) = value.to_str() {
+                *value = HeaderValue::from_str(&interpolate_str(s, vars))?;
+            }
+        }
+        if let Some(params) = &mut profile.params {
+            interpolate(params, vars);
+        }
+        if let Some(body) = &mut profile.body {
+            interpolate(body, vars);
+        }
+        if let Some(graphql) = &mut profile.graphql {
+            if let Some(query) = &mut graphql.query {
+                *query = interpolate_str(query, vars);
+            }
+            if let Some(variables) = &mut graphql.variables {
+                interpolate(variables, vars);
+            }
+        }
+        if let Some(grpc) = &mut profile.grpc {
+            interpolate(&mut grpc.request, vars);
+        }
+        if let Some(websocket) = &mut profile.websocket {
+            websocket
+                .send
+                .iter_mut()
+                .for_each(|frame| interpolate(frame, vars));
+        }
+        Ok(profile)
+    }
+}
+
+impl GraphqlProfile {
+    /// The `{"query", "variables", "operationName"}` body of the request.
+    async fn envelope(&self) -> Result<Value> {
+        let query = match (&self.query, &self.query_file) {
+            (Some(query), None) => query.clone(),
+            (None, Some(path)) => {
+                let path = match &self.base {
+                    Some(base) => base.join(path),
+                    None => path.clone(),
+                };
+                fs::read_to_string(&path)
+                    .await
+                    .with_context(|| format!("failed to read query file {}", path.display()))?
+            }
+            _ => bail!("graphql needs exactly one of query and query_file"),
+        };
+        let mut envelope = json!({ "query": query });
+        if let Some(variables) = &self.variables {
+            envelope["variables"] = variables.clone();
+        }
+        if let Some(name) = &self.operation_name {
+            envelope["operationName"] = json!(name);
+        }
+        Ok(envelope)
+    }
+}
+
+impl ResponseData {
+    /// Read the first `limit` bytes of a body, decoded lossily as UTF-8.
+    async fn read_limited(mut res: Response, limit: u64) -> Result<Self> {
+        let (status, version, headers) = (res.status(), res.version(), res.headers().clone());
+        let mut body = Vec::new();
+        let mut truncated = None;
+        while let Some(chunk) = res.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() as u64 > limit {
+                truncated = Some(Truncated {
+                    read: body.len() as u64,
+                    kept: limit,
+                    limited: true,
+                    hidden_regions: 0,
+                });
+                body.truncate(limit as usize);
+                break;
+            }
+        }
+        Ok(Self {
+            status,
+            version,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+            truncated,
+        })
+    }
+
+    /// The body parsed as JSON if it is, or as a string otherwise.
+    pub fn json_body(&self) -> Value {
+        match self.is_json() {
+            true => serde_json::from_str(&self.body).unwrap_or_else(|_| json!(self.body)),
+            false => json!(self.body),
+        }
+    }
+
+    /// The response as a JSON object of `status`, `version`, `headers`, with repeated ones as
+    /// arrays, and `body`, parsed if it is JSON.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "status": self.status.as_u16(),
+            "version": format!("{:?}", self.version),
+            "headers": headers_to_json(&self.headers),
+            "body": self.json_body(),
+        })
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|ct| {
+                let mime = ct.split(';').next().unwrap_or_default().trim();
+                mime == "application/json" || mime.ends_with("+json")
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl FromStr for Override {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected key=value, got `{}`", s))?;
+        let value = value.to_string();
+        Ok(match key.chars().next() {
+            Some('%') => Self::Header(key[1..].to_string(), value),
+            Some('@') => Self::Body(
+                key[1..].to_string(),
+                serde_json::from_str(&value).unwrap_or(Value::String(value)),
+            ),
+            _ => Self::Query(key.to_string(), value),
+        })
+    }
+}
+
+impl std::fmt::Display for Truncated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.limited {
+            write!(
+                f,
+                "stopped at max_body_size after reading {} bytes, kept {}",
+                self.read, self.kept
+            )?;
+        } else {
+            write!(f, "kept {} of {} bytes", self.kept, self.read)?;
+        }
+        if self.hidden_regions > 0 {
+            write!(
+                f,
+                ", {} more differing regions not shown",
+                self.hidden_regions
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+pub(crate) fn headers_to_json(headers: &HeaderMap) -> Value {
+    let mut map = Map::new();
+    for name in headers.keys() {
+        let values: Vec<_> = headers
+            .get_all(name)
+            .iter()
+            .map(|v| Value::String(String::from_utf8_lossy(v.as_bytes()).into_owned()))
+            .collect();
+        let value = match values.len() {
+            1 => values.into_iter().next().unwrap_or_default(),
+            _ => Value::Array(values),
+        };
+        map.insert(name.to_string(), value);
+    }
+    Value::Object(map)
+}
+
+/// Convert headers from the other version of the http crate used by tonic and tungstenite.
+pub(crate) fn header_map<'a>(
+    headers: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+) -> Result<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        map.append(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_bytes(value)?,
+        );
+    }
+    Ok(map)
+}
+
+fn query_pairs(params: &Value) -> Vec<(String, String)> {
+    match params {
+        Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| {
+                let v = match v {
+                    Value::String(s) => s.clone(),
+                    v => v.to_string(),
+                };
+                (k.clone(), v)
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,166 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Method, StatusCode,
+};
+use rhai::{module_resolvers::DummyModuleResolver, Dynamic, Engine, Scope, AST};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{req::headers_to_json, signing::hmac, RequestProfile, ResponseData};
+
+/// Operations a script may run before it is stopped, so that a loop can't hang a case.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Rhai scripts run around a request. `pre` gets the outgoing request as `request`, a map of
+/// `method`, `url`, `headers` and `body`, and `post` the received response as `response`, a
+/// map of `status`, `headers` and `body`. Whatever the script leaves in the variable is used
+/// instead. Headers map names to a string, or to an array of strings for repeated headers, and
+/// JSON bodies are given as maps and arrays.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct ScriptProfile {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pre: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub post: Option<String>,
+}
+
+/// A compiled rhai script. It runs in an engine without filesystem or module access, `eval`
+/// or unbounded loops; `print` and `debug` write to stderr.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptProfile {
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_none() && self.post.is_none()
+    }
+
+    /// The request as rewritten by `pre`.
+    pub(crate) fn before(&self, req: &RequestProfile) -> Result<Option<RequestProfile>> {
+        let code = match &self.pre {
+            Some(code) => code,
+            None => return Ok(None),
+        };
+        let value = json!({
+            "method": req.method().as_str(),
+            "url": req.full_url().as_str(),
+            "headers": headers_to_json(&req.headers),
+            "body": req.body,
+        });
+        let value = Script::new(code)?.run("request", value)?;
+
+        let mut req = req.clone();
+        if let Some(method) = value.get("method").and_then(Value::as_str) {
+            req.method = Method::from_bytes(method.as_bytes())?;
+        }
+        if let Some(url) = value.get("url").and_then(Value::as_str) {
+            req.url = url.parse()?;
+            req.params = None;
+        }
+        if let Some(headers) = value.get("headers") {
+            req.headers = json_to_headers(headers)?;
+        }
+        req.body = value.get("body").filter(|v| !v.is_null()).cloned();
+        Ok(Some(req))
+    }
+
+    /// The response as rewritten by `post`.
+    pub(crate) fn after(&self, mut res: ResponseData) -> Result<ResponseData> {
+        let code = match &self.post {
+            Some(code) => code,
+            None => return Ok(res),
+        };
+        let value = json!({
+            "status": res.status.as_u16(),
+            "headers": headers_to_json(&res.headers),
+            "body": res.json_body(),
+        });
+        let value = Script::new(code)?.run("response", value)?;
+
+        if let Some(status) = value.get("status").and_then(Value::as_u64) {
+            res.status = StatusCode::from_u16(status as u16)?;
+        }
+        if let Some(headers) = value.get("headers") {
+            res.headers = json_to_headers(headers)?;
+        }
+        res.body = match value.get("body") {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(v) => serde_json::to_string(v)?,
+        };
+        Ok(res)
+    }
+}
+
+impl Script {
+    pub fn new(code: &str) -> Result<Self> {
+        let engine = engine();
+        let ast = engine
+            .compile(code)
+            .map_err(|e| anyhow!("failed to compile script: {}", e))?;
+        Ok(Self { engine, ast })
+    }
+
+    /// Run the script with `name` bound to `value`, returning the variable as it was left.
+    pub fn run(&self, name: &str, value: Value) -> Result<Value> {
+        let mut scope = Scope::new();
+        scope.push_dynamic(name, rhai::serde::to_dynamic(value)?);
+        self.engine
+            .run_ast_with_scope(&mut scope, &self.ast)
+            .map_err(|e| anyhow!("script failed: {}", e))?;
+        let value = scope.get_value::<Dynamic>(name).unwrap_or_default();
+        rhai::serde::from_dynamic(&value)
+            .map_err(|e| anyhow!("script left invalid {}: {}", name, e))
+    }
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .disable_symbol("eval")
+        .set_max_operations(MAX_OPERATIONS)
+        .on_print(|s| eprintln!("{}", s))
+        .on_debug(|s, _, pos| eprintln!("{:?} {}", pos, s));
+    engine
+        .register_fn("sha256_hex", |data: &str| {
+            hex::encode(Sha256::digest(data.as_bytes()))
+        })
+        .register_fn("hmac_sha256_hex", |key: &str, data: &str| {
+            hex::encode(hmac(key.as_bytes(), data.as_bytes()))
+        })
+        .register_fn("unix_time", || {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64)
+        });
+    engine
+}
+
+fn json_to_headers(value: &Value) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    let map = value
+        .as_object()
+        .ok_or_else(|| anyhow!("script headers must be a map"))?;
+    for (name, value) in map {
+        let name = HeaderName::from_bytes(name.as_bytes())?;
+        let values = match value {
+            Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            let value = match value {
+                Value::String(s) => HeaderValue::from_str(s)?,
+                v => HeaderValue::from_str(&v.to_string())?,
+            };
+            headers.append(name.clone(), value);
+        }
+    }
+    Ok(headers)
+}
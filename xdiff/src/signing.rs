@@ -0,0 +1,279 @@
+use std::{env, time::SystemTime};
+
+use anyhow::{anyhow, bail, Context, Result};
+use hmac::{Hmac, KeyInit, Mac};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Method, Request, Url,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// How a request is signed, with exactly one of the schemes. The signature is computed last,
+/// over the request exactly as sent, so command line overrides and scripts are covered by it.
+/// Keys are read from the environment.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct SigningProfile {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub hmac: Option<HmacSigning>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub aws_sigv4: Option<AwsSigning>,
+}
+
+/// An HMAC-SHA256 over the method, path, sorted query, the `headers` listed and the hex
+/// SHA-256 of the body, one per line. It is sent hex-encoded in `header`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct HmacSigning {
+    /// The environment variable holding the key.
+    pub key_env: String,
+    #[serde(default = "default_signature_header")]
+    pub header: String,
+    /// Headers included in the signature, as `name:value` lines in this order.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub headers: Vec<String>,
+    /// Set this header to the current unix time and sign it, so that signatures expire.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timestamp_header: Option<String>,
+}
+
+/// AWS Signature Version 4, sent in the `authorization` header. Credentials come from
+/// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and, if set, `AWS_SESSION_TOKEN`, or from the
+/// variables named here.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct AwsSigning {
+    pub region: String,
+    pub service: String,
+    /// Headers signed on top of `host` and the `x-amz-*` ones.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub headers: Vec<String>,
+    #[serde(default = "default_access_key_env")]
+    pub access_key_env: String,
+    #[serde(default = "default_secret_key_env")]
+    pub secret_key_env: String,
+    #[serde(default = "default_session_token_env")]
+    pub session_token_env: String,
+}
+
+fn default_signature_header() -> String {
+    "x-signature".into()
+}
+
+fn default_access_key_env() -> String {
+    "AWS_ACCESS_KEY_ID".into()
+}
+
+fn default_secret_key_env() -> String {
+    "AWS_SECRET_ACCESS_KEY".into()
+}
+
+fn default_session_token_env() -> String {
+    "AWS_SESSION_TOKEN".into()
+}
+
+impl SigningProfile {
+    /// Sign a request that is ready to be sent, adding the signature headers to it.
+    pub fn sign(&self, req: &mut Request, now: SystemTime) -> Result<()> {
+        self.sign_with(req, now, &|name| env::var(name).ok())
+    }
+
+    /// Like [`SigningProfile::sign`], with the credentials looked up by `env` instead of in the
+    /// environment.
+    pub fn sign_with(&self, req: &mut Request, now: SystemTime, env: &Env) -> Result<()> {
+        let body = match req.body() {
+            Some(body) => body
+                .as_bytes()
+                .ok_or_else(|| anyhow!("can't sign a streaming body"))?
+                .to_vec(),
+            None => Vec::new(),
+        };
+        let (method, url) = (req.method().clone(), req.url().clone());
+        match (&self.hmac, &self.aws_sigv4) {
+            (Some(hmac), None) => hmac.sign(&method, &url, req.headers_mut(), &body, now, env),
+            (None, Some(aws)) => aws.sign(&method, &url, req.headers_mut(), &body, now, env),
+            _ => bail!("signing needs exactly one of hmac and aws_sigv4"),
+        }
+    }
+}
+
+impl HmacSigning {
+    fn sign(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &mut HeaderMap,
+        body: &[u8],
+        now: SystemTime,
+        env: &Env,
+    ) -> Result<()> {
+        let key = required(env, &self.key_env)?;
+        let mut names = self.headers.clone();
+        if let Some(name) = &self.timestamp_header {
+            let secs = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+            headers.insert(HeaderName::from_bytes(name.as_bytes())?, secs.into());
+            names.push(name.clone());
+        }
+
+        let mut lines = vec![
+            method.to_string(),
+            url.path().to_string(),
+            canonical_query(url),
+        ];
+        for name in &names {
+            let name = name.to_ascii_lowercase();
+            lines.push(format!("{}:{}", name, header_values(headers, &name)?));
+        }
+        lines.push(hex::encode(Sha256::digest(body)));
+
+        let signature = hex::encode(hmac(key.as_bytes(), lines.join("\n").as_bytes()));
+        headers.insert(
+            HeaderName::from_bytes(self.header.as_bytes())?,
+            HeaderValue::from_str(&signature)?,
+        );
+        Ok(())
+    }
+}
+
+impl AwsSigning {
+    fn sign(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &mut HeaderMap,
+        body: &[u8],
+        now: SystemTime,
+        env: &Env,
+    ) -> Result<()> {
+        let access_key = required(env, &self.access_key_env)?;
+        let secret_key = required(env, &self.secret_key_env)?;
+        // e.g. 2015-08-30T12:36:00Z becomes 20150830T123600Z.
+        let timestamp: String = humantime::format_rfc3339_seconds(now)
+            .to_string()
+            .chars()
+            .filter(|c| *c != '-' && *c != ':')
+            .collect();
+        let date = &timestamp[..8];
+        let payload_hash = hex::encode(Sha256::digest(body));
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        headers.insert("host", HeaderValue::from_str(&host)?);
+        headers.insert("x-amz-date", HeaderValue::from_str(&timestamp)?);
+        let mut names = vec!["host".to_string(), "x-amz-date".to_string()];
+        if self.service == "s3" {
+            headers.insert(
+                "x-amz-content-sha256",
+                HeaderValue::from_str(&payload_hash)?,
+            );
+            names.push("x-amz-content-sha256".into());
+        }
+        if let Some(token) = env(&self.session_token_env) {
+            headers.insert("x-amz-security-token", HeaderValue::from_str(&token)?);
+            names.push("x-amz-security-token".into());
+        }
+        names.extend(self.headers.iter().map(|h| h.to_ascii_lowercase()));
+        names.sort();
+        names.dedup();
+
+        let mut canonical_headers = String::new();
+        for name in &names {
+            let value = header_values(headers, name)?;
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            canonical_headers.push_str(&format!("{}:{}\n", name, value));
+        }
+        let signed_headers = names.join(";");
+        let canonical_request = [
+            method.as_str(),
+            &self.canonical_path(url),
+            &canonical_query(url),
+            &canonical_headers,
+            &signed_headers,
+            &payload_hash,
+        ]
+        .join("\n");
+
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut key = hmac(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes());
+        for part in [self.region.as_str(), self.service.as_str(), "aws4_request"] {
+            key = hmac(&key, part.as_bytes());
+        }
+        let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            access_key, scope, signed_headers, signature
+        );
+        headers.insert("authorization", HeaderValue::from_str(&authorization)?);
+        Ok(())
+    }
+
+    /// The path, with each segment encoded once more except for S3.
+    fn canonical_path(&self, url: &Url) -> String {
+        if self.service == "s3" {
+            return url.path().to_string();
+        }
+        url.path()
+            .split('/')
+            .map(uri_encode)
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Looks up an environment variable, as [`std::env::var`] does.
+pub type Env = dyn Fn(&str) -> Option<String>;
+
+fn required(env: &Env, name: &str) -> Result<String> {
+    env(name).with_context(|| format!("environment variable {} is not set", name))
+}
+
+pub(crate) fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// The values of a header joined by commas, which must all be valid strings.
+fn header_values(headers: &HeaderMap, name: &str) -> Result<String> {
+    let values = headers
+        .get_all(name)
+        .iter()
+        .map(|v| v.to_str().map(str::trim))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("header {} can't be signed", name))?;
+    Ok(values.join(","))
+}
+
+/// The query pairs, encoded and sorted by name then value.
+fn canonical_query(url: &Url) -> String {
+    let mut pairs: Vec<_> = url
+        .query_pairs()
+        .map(|(k, v)| (uri_encode(&k), uri_encode(&v)))
+        .collect();
+    pairs.sort();
+    pairs
+        .into_iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Percent-encode everything but the unreserved characters of RFC 3986.
+fn uri_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
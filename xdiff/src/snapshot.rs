@@ -0,0 +1,75 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::ResponseData;
+
+/// The raw responses of both sides of a profile, stored as `<dir>/<profile>.json`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    pub req1: ResponseData,
+    pub req2: ResponseData,
+}
+
+impl Snapshot {
+    pub fn new(req1: ResponseData, req2: ResponseData) -> Self {
+        Self { req1, req2 }
+    }
+
+    pub async fn load(dir: impl AsRef<Path>, name: &str) -> Result<Self> {
+        let path = Self::path(dir.as_ref(), name);
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("failed to read snapshot {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse snapshot {}", path.display()))
+    }
+
+    pub async fn save(&self, dir: impl AsRef<Path>, name: &str) -> Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir).await?;
+        fs::write(Self::path(dir, name), serde_json::to_string_pretty(self)?).await?;
+        Ok(())
+    }
+
+    /// Fail if cases with different names would be stored in the same snapshot file, where
+    /// one would silently overwrite the other.
+    pub fn check_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        let mut files: HashMap<String, &str> = HashMap::new();
+        for name in names {
+            let file = Self::file_name(name);
+            match files.get(&file) {
+                Some(other) if *other != name => bail!(
+                    "cases `{}` and `{}` would share the snapshot {}, rename one of them",
+                    other,
+                    name,
+                    file
+                ),
+                _ => {
+                    files.insert(file, name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn path(dir: &Path, name: &str) -> PathBuf {
+        dir.join(Self::file_name(name))
+    }
+
+    fn file_name(name: &str) -> String {
+        let file: String = name
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+                _ => '_',
+            })
+            .collect();
+        format!("{}.json", file)
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::Result;
+use reqwest::Response;
+
+use crate::{req::Truncated, DiffArgs, DiffProfile, ResponseData};
+
+/// Bodies are compared in chunks of this many bytes.
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Differing chunks kept per region, the rest of a long region is skipped.
+const REGION_CHUNKS: usize = 4;
+/// Regions kept before the remaining ones are only counted.
+const MAX_REGIONS: usize = 8;
+
+/// A chunk of a body and its offset.
+type Chunk = (u64, Vec<u8>);
+
+/// One side of a streamed comparison: the response being read, and the windows kept so far.
+struct Side {
+    res: Response,
+    buf: Vec<u8>,
+    done: bool,
+    read: u64,
+    limited: bool,
+    kept: Vec<u8>,
+    /// Bytes of the body in `kept`, not counting the markers.
+    kept_bytes: u64,
+    kept_to: u64,
+}
+
+/// Fetch both bodies chunk by chunk, comparing chunks as they arrive. Only windows around the
+/// regions where the bodies differ are kept: the equal chunk before each region, its first
+/// differing chunks and the equal chunk after it. Skipped ranges are marked with the offset
+/// they skip to, so that aligned skips look the same on both sides, and regions past
+/// `MAX_REGIONS` are counted in a marker at the end. Chunks are compared at the same offsets,
+/// so bytes inserted on one side make the rest of the body a single region, cut after its
+/// first chunks.
+pub(crate) async fn fetch(
+    profile: &DiffProfile,
+    args: &DiffArgs,
+) -> Result<(ResponseData, ResponseData)> {
+    let limit = profile.res.max_body_size;
+    // Both requests stay in flight until their bodies are read.
+    let _permit = args
+        .limiter
+        .acquire_all(&[&profile.req1.url, &profile.req2.url])
+        .await;
+    let (res1, res2) = tokio::try_join!(profile.req1.request(args), profile.req2.request(args))?;
+    let (mut side1, mut side2) = (Side::new(res1), Side::new(res2));
+
+    let mut before: Option<(Chunk, Chunk)> = None;
+    let mut region: Option<usize> = None;
+    let mut regions = 0;
+    // Regions past MAX_REGIONS, and whether the last chunk was part of one.
+    let (mut hidden, mut in_hidden) = (0, false);
+    loop {
+        let (chunk1, chunk2) = tokio::try_join!(side1.next(limit), side2.next(limit))?;
+        if chunk1.1.is_empty() && chunk2.1.is_empty() {
+            break;
+        }
+        if chunk1.1 == chunk2.1 {
+            if region.take().is_some() {
+                side1.keep(chunk1);
+                side2.keep(chunk2);
+            } else {
+                before = Some((chunk1, chunk2));
+            }
+            in_hidden = false;
+            continue;
+        }
+        match region {
+            Some(n) if n < REGION_CHUNKS => {
+                side1.keep(chunk1);
+                side2.keep(chunk2);
+                region = Some(n + 1);
+            }
+            Some(_) => {}
+            None if regions < MAX_REGIONS => {
+                if let Some((before1, before2)) = before.take() {
+                    side1.keep(before1);
+                    side2.keep(before2);
+                }
+                side1.keep(chunk1);
+                side2.keep(chunk2);
+                region = Some(1);
+                regions += 1;
+            }
+            None if !in_hidden => {
+                hidden += 1;
+                in_hidden = true;
+            }
+            None => {}
+        }
+    }
+    Ok((
+        profile.req1.script.after(side1.finish(hidden))?,
+        profile.req2.script.after(side2.finish(hidden))?,
+    ))
+}
+
+impl Side {
+    fn new(res: Response) -> Self {
+        Self {
+            res,
+            buf: Vec::new(),
+            done: false,
+            read: 0,
+            limited: false,
+            kept: Vec::new(),
+            kept_bytes: 0,
+            kept_to: 0,
+        }
+    }
+
+    /// The next chunk and its offset. The chunk is empty at the end of the body, or once
+    /// `limit` bytes were read.
+    async fn next(&mut self, limit: Option<u64>) -> Result<Chunk> {
+        while !self.done && self.buf.len() < CHUNK_SIZE {
+            match self.res.chunk().await? {
+                Some(bytes) => self.buf.extend_from_slice(&bytes),
+                None => self.done = true,
+            }
+        }
+        let mut len = self.buf.len().min(CHUNK_SIZE);
+        if let Some(limit) = limit {
+            let left = limit.saturating_sub(self.read) as usize;
+            if len > left {
+                len = left;
+                self.limited = true;
+            }
+        }
+        let offset = self.read;
+        self.read += len as u64;
+        Ok((offset, self.buf.drain(..len).collect()))
+    }
+
+    fn keep(&mut self, (offset, chunk): Chunk) {
+        if offset > self.kept_to {
+            let marker = format!("\n[... skipped to byte {} ...]\n", offset);
+            self.kept.extend_from_slice(marker.as_bytes());
+        }
+        self.kept.extend_from_slice(&chunk);
+        self.kept_bytes += chunk.len() as u64;
+        self.kept_to = offset + chunk.len() as u64;
+    }
+
+    /// The kept windows as the body, followed by a marker for the `hidden` regions that were
+    /// not kept. It counts as truncated whenever anything was left out.
+    fn finish(mut self, hidden: usize) -> ResponseData {
+        if hidden > 0 {
+            let marker = format!("\n[... {} more differing regions not shown ...]\n", hidden);
+            self.kept.extend_from_slice(marker.as_bytes());
+        }
+        // Bytes buffered past the limit were read from the server too.
+        let read = self.read + self.buf.len() as u64;
+        let truncated = (self.kept_bytes < read).then_some(Truncated {
+            read,
+            kept: self.kept_bytes,
+            limited: self.limited,
+            hidden_regions: hidden,
+        });
+        ResponseData {
+            status: self.res.status(),
+            version: self.res.version(),
+            headers: self.res.headers().clone(),
+            body: String::from_utf8_lossy(&self.kept).into_owned(),
+            truncated,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use anyhow::{anyhow, bail, Result};
+use jaq_core::{
+    load::{Arena, File, Loader},
+    Compiler, Ctx, Filter, Native, RcIter,
+};
+use jaq_json::Val;
+use serde_json::Value;
+
+/// A compiled jq filter (via jaq) used to reshape a response body before it is compared.
+pub struct Transform {
+    code: String,
+    filter: Filter<Native<Val>>,
+}
+
+impl Transform {
+    pub fn new(code: &str) -> Result<Self> {
+        let program = File { code, path: () };
+        let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
+        let arena = Arena::default();
+        let modules = loader.load(&arena, program).map_err(|errs| {
+            let errs: Vec<_> = errs.into_iter().map(|(_, e)| e).collect();
+            anyhow!("failed to parse transform `{}`: {:?}", code, errs)
+        })?;
+        let filter = Compiler::default()
+            .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+            .compile(modules)
+            .map_err(|errs| {
+                let errs: Vec<_> = errs.into_iter().map(|(_, e)| e).collect();
+                anyhow!("failed to compile transform `{}`: {:?}", code, errs)
+            })?;
+        Ok(Self {
+            code: code.to_string(),
+            filter,
+        })
+    }
+
+    /// Run the filter on `input`. A filter yielding several values produces an array of them.
+    pub fn apply(&self, input: Value) -> Result<Value> {
+        let inputs = RcIter::new(core::iter::empty());
+        let mut outputs = self
+            .filter
+            .run((Ctx::new([], &inputs), Val::from(input)))
+            .map(|v| {
+                v.map(Value::from)
+                    .map_err(|e| anyhow!("transform `{}` failed: {}", self.code, e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        match outputs.len() {
+            0 => bail!("transform `{}` produced no output", self.code),
+            1 => Ok(outputs.remove(0)),
+            _ => Ok(Value::Array(outputs)),
+        }
+    }
+}
@@ -0,0 +1,650 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+use serde_json::Value;
+use similar::{capture_diff_slices, group_diff_ops, ChangeTag, TextDiff};
+use tokio::fs;
+
+use crate::{diff_text, DiffArgs, DiffCase, DiffConfig, DiffOptions, Outcome, ResponseData};
+
+/// A case with its responses fetched once; every re-diff works off this cache.
+pub struct TuiCase {
+    pub profile: String,
+    pub case: DiffCase,
+    pub responses: Result<(ResponseData, ResponseData), String>,
+    outcome: Outcome,
+    hunks: Vec<Vec<Line<'static>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Rule {
+    Header(String),
+    Body(String),
+}
+
+enum Mode {
+    Browse,
+    Rules,
+}
+
+/// Browse the results of many profiles, step through hunks and tune skip rules interactively.
+pub struct App {
+    config: DiffConfig,
+    path: PathBuf,
+    cases: Vec<TuiCase>,
+    list: ListState,
+    hunk: usize,
+    mode: Mode,
+    rules: Vec<Rule>,
+    rule_list: ListState,
+    message: String,
+    dirty: bool,
+    quitting: bool,
+    /// Whether the original config was copied aside by an earlier save.
+    backed_up: bool,
+    args: DiffArgs,
+}
+
+impl TuiCase {
+    pub fn new(
+        profile: String,
+        case: DiffCase,
+        responses: Result<(ResponseData, ResponseData), String>,
+        args: &DiffArgs,
+    ) -> Self {
+        let mut case = Self {
+            profile,
+            case,
+            responses,
+            outcome: Outcome::Equal,
+            hunks: Vec::new(),
+        };
+        case.rediff(args);
+        case
+    }
+
+    fn rediff(&mut self, args: &DiffArgs) {
+        let texts = match &self.responses {
+            Ok((res1, res2)) => self.case.profile.filter_text(res1, res2),
+            Err(e) => {
+                self.outcome = Outcome::Error { error: e.clone() };
+                self.hunks.clear();
+                return;
+            }
+        };
+        match texts {
+            Ok((text1, text2)) => {
+                let options = args.options(&self.case.profile.res.diff);
+                self.outcome = Outcome::from_diff(diff_text(&text1, &text2, &options));
+                self.hunks = match self.outcome {
+                    Outcome::Different { .. } => hunks(&text1, &text2, &options),
+                    _ => Vec::new(),
+                };
+            }
+            Err(e) => {
+                self.outcome = Outcome::Error {
+                    error: format!("{:#}", e),
+                };
+                self.hunks.clear();
+            }
+        }
+    }
+
+    /// Headers and top-level body fields that could be skipped, from both sides.
+    fn candidates(&self) -> Vec<Rule> {
+        let mut rules = Vec::new();
+        let (res1, res2) = match &self.responses {
+            Ok(responses) => responses,
+            Err(_) => return rules,
+        };
+        for res in [res1, res2] {
+            for name in res.headers.keys() {
+                let rule = Rule::Header(name.as_str().to_string());
+                if !rules.contains(&rule) {
+                    rules.push(rule);
+                }
+            }
+        }
+        for res in [res1, res2] {
+            if let Ok(Value::Object(map)) = serde_json::from_str::<Value>(&res.body) {
+                for key in map.keys() {
+                    let rule = Rule::Body(key.clone());
+                    if !rules.contains(&rule) {
+                        rules.push(rule);
+                    }
+                }
+            }
+        }
+        rules
+    }
+}
+
+impl App {
+    pub fn new(config: DiffConfig, path: PathBuf, cases: Vec<TuiCase>, args: DiffArgs) -> Self {
+        let mut list = ListState::default();
+        if !cases.is_empty() {
+            list.select(Some(0));
+        }
+        Self {
+            config,
+            path,
+            cases,
+            list,
+            hunk: 0,
+            mode: Mode::Browse,
+            rules: Vec::new(),
+            rule_list: ListState::default(),
+            message: String::new(),
+            dirty: false,
+            quitting: false,
+            backed_up: false,
+            args,
+        }
+    }
+
+    pub async fn run(mut self) -> Result<()> {
+        let mut terminal = ratatui::init();
+        let result = self.event_loop(&mut terminal).await;
+        ratatui::restore();
+        result
+    }
+
+    async fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            // Reading blocks until a key is pressed, which must not hold up the runtime.
+            let key = match tokio::task::spawn_blocking(event::read).await?? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            self.message.clear();
+            match self.mode {
+                Mode::Browse => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => {
+                        if !self.dirty || self.quitting {
+                            return Ok(());
+                        }
+                        self.quitting = true;
+                        self.message = "Unsaved skip rules, press q again to quit".to_string();
+                        continue;
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => self.select(1),
+                    KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+                    KeyCode::Char('n') | KeyCode::Right => self.step_hunk(1),
+                    KeyCode::Char('p') | KeyCode::Left => self.step_hunk(-1),
+                    KeyCode::Char('s') => self.open_rules(),
+                    KeyCode::Char('w') => {
+                        if let Err(e) = self.save().await {
+                            self.message = format!("Failed to save: {:#}", e);
+                        }
+                    }
+                    _ => {}
+                },
+                Mode::Rules => match key.code {
+                    KeyCode::Esc | KeyCode::Char('s') | KeyCode::Char('q') => {
+                        self.mode = Mode::Browse
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        step(&mut self.rule_list, self.rules.len(), 1)
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        step(&mut self.rule_list, self.rules.len(), -1)
+                    }
+                    KeyCode::Char(' ') | KeyCode::Enter => self.toggle_rule(),
+                    _ => {}
+                },
+            }
+            self.quitting = false;
+        }
+    }
+
+    fn current(&self) -> Option<&TuiCase> {
+        self.list.selected().and_then(|i| self.cases.get(i))
+    }
+
+    fn select(&mut self, delta: isize) {
+        step(&mut self.list, self.cases.len(), delta);
+        self.hunk = 0;
+    }
+
+    fn step_hunk(&mut self, delta: isize) {
+        let total = self.current().map_or(0, |c| c.hunks.len());
+        if total > 0 {
+            self.hunk = (self.hunk as isize + delta).rem_euclid(total as isize) as usize;
+        }
+    }
+
+    fn open_rules(&mut self) {
+        if let Some(case) = self.current() {
+            self.rules = case.candidates();
+            self.rule_list
+                .select(if self.rules.is_empty() { None } else { Some(0) });
+            self.mode = Mode::Rules;
+        }
+    }
+
+    fn toggle_rule(&mut self) {
+        let (Some(idx), Some(current)) = (self.rule_list.selected(), self.list.selected()) else {
+            return;
+        };
+        let profile = self.cases[current].profile.clone();
+        let mut res = self.cases[current].case.profile.res.clone();
+        match &self.rules[idx] {
+            Rule::Header(name) => toggle(&mut res.skip_headers, name),
+            Rule::Body(name) => toggle(&mut res.skip_body, name),
+        }
+        // Skip rules belong to the profile, so every case of it is re-diffed.
+        for case in self.cases.iter_mut().filter(|c| c.profile == profile) {
+            case.case.profile.res = res.clone();
+            case.rediff(&self.args);
+        }
+        if let Some(p) = self.config.profiles.get_mut(&profile) {
+            p.res = res;
+        }
+        self.hunk = 0;
+        self.dirty = true;
+    }
+
+    /// Write the config with the new skip rules back to its file. The first save copies the
+    /// original aside, as its comments and layout don't survive.
+    async fn save(&mut self) -> Result<()> {
+        let content = self.config.to_string_for(&self.path)?;
+        let backup = self.backup_path();
+        if !self.backed_up {
+            fs::copy(&self.path, &backup)
+                .await
+                .with_context(|| format!("failed to back up {}", self.path.display()))?;
+            self.backed_up = true;
+        }
+        fs::write(&self.path, content)
+            .await
+            .with_context(|| format!("failed to write {}", self.path.display()))?;
+        self.dirty = false;
+        self.message = format!(
+            "Saved {}, the original is in {}",
+            self.path.display(),
+            backup.display()
+        );
+        Ok(())
+    }
+
+    /// `xdiff.yml` is backed up as `xdiff.yml.bak`.
+    fn backup_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".bak");
+        self.path.with_file_name(name)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)])
+                .areas(main);
+
+        let items: Vec<ListItem> = self
+            .cases
+            .iter()
+            .map(|c| {
+                let (mark, color) = match c.outcome {
+                    Outcome::Equal => ("✓", Color::Green),
+                    Outcome::Different { .. } => ("✗", Color::Red),
+                    Outcome::Error { .. } => ("!", Color::Yellow),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{} ", mark), Style::new().fg(color)),
+                    Span::raw(c.case.name.clone()),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(" Profiles "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, left, &mut self.list);
+
+        match self.mode {
+            Mode::Browse => self.draw_hunk(frame, right),
+            Mode::Rules => self.draw_rules(frame, right),
+        }
+
+        let help = match self.mode {
+            Mode::Browse => "↑/↓ profile  ←/→ hunk  s skip rules  w save  q quit",
+            Mode::Rules => "↑/↓ rule  space toggle  esc back",
+        };
+        let text = if self.message.is_empty() {
+            help.to_string()
+        } else {
+            self.message.clone()
+        };
+        frame.render_widget(
+            Paragraph::new(text).style(Style::new().add_modifier(Modifier::DIM)),
+            status,
+        );
+    }
+
+    fn draw_hunk(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let Some(case) = self.current() else {
+            frame.render_widget(Block::bordered(), area);
+            return;
+        };
+        let (title, lines) = match &case.outcome {
+            Outcome::Error { error } => (
+                format!(" {} ", case.case.name),
+                vec![Line::styled(error.clone(), Style::new().fg(Color::Yellow))],
+            ),
+            _ if case.hunks.is_empty() => (
+                format!(" {} ", case.case.name),
+                vec![Line::raw("No differences found.")],
+            ),
+            _ => {
+                let idx = self.hunk.min(case.hunks.len() - 1);
+                (
+                    format!(
+                        " {} — hunk {}/{} ",
+                        case.case.name,
+                        idx + 1,
+                        case.hunks.len()
+                    ),
+                    case.hunks[idx].clone(),
+                )
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+
+    fn draw_rules(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let Some(case) = self.current() else {
+            return;
+        };
+        let res = &case.case.profile.res;
+        let items: Vec<ListItem> = self
+            .rules
+            .iter()
+            .map(|rule| {
+                let (kind, name, on) = match rule {
+                    Rule::Header(name) => ("header", name, res.skip_headers.contains(name)),
+                    Rule::Body(name) => ("body", name, res.skip_body.contains(name)),
+                };
+                let mark = if on { "[x]" } else { "[ ]" };
+                ListItem::new(format!("{} {:<7}{}", mark, kind, name))
+            })
+            .collect();
+        let title = format!(" Skip rules for {} ", case.profile);
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.rule_list);
+    }
+}
+
+fn step(state: &mut ListState, len: usize, delta: isize) {
+    if len == 0 {
+        return;
+    }
+    let current = state.selected().unwrap_or(0) as isize;
+    state.select(Some((current + delta).rem_euclid(len as isize) as usize));
+}
+
+fn toggle(rules: &mut Vec<String>, name: &str) {
+    match rules.iter().position(|r| r == name) {
+        Some(pos) => {
+            rules.remove(pos);
+        }
+        None => rules.push(name.to_string()),
+    }
+}
+
+/// Split the line diff into hunks rendered in the same style as `diff_text`.
+fn hunks(text1: &str, text2: &str, options: &DiffOptions) -> Vec<Vec<Line<'static>>> {
+    if options.ignore_whitespace || options.ignore_case {
+        return normalized_hunks(text1, text2, options);
+    }
+    let diff = TextDiff::configure()
+        .algorithm(options.algorithm.into())
+        .diff_lines(text1, text2);
+    diff.grouped_ops(options.context)
+        .iter()
+        .map(|group| {
+            group
+                .iter()
+                .flat_map(|op| diff.iter_inline_changes(op))
+                .map(|change| {
+                    let pieces = change
+                        .iter_strings_lossy()
+                        .map(|(emphasized, value)| {
+                            (emphasized, value.trim_end_matches('\n').to_string())
+                        })
+                        .collect();
+                    change_line(change.tag(), change.old_index(), change.new_index(), pieces)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Hunks of a line diff where lines are compared after applying the ignore options, but shown
+/// as is, like `diff_text` does.
+fn normalized_hunks(text1: &str, text2: &str, options: &DiffOptions) -> Vec<Vec<Line<'static>>> {
+    let old: Vec<&str> = text1.split_inclusive('\n').collect();
+    let new: Vec<&str> = text2.split_inclusive('\n').collect();
+    let old_keys: Vec<String> = old.iter().map(|l| options.normalize(l)).collect();
+    let new_keys: Vec<String> = new.iter().map(|l| options.normalize(l)).collect();
+    let ops = capture_diff_slices(options.algorithm.into(), &old_keys, &new_keys);
+    group_diff_ops(ops, options.context)
+        .iter()
+        .map(|group| {
+            group
+                .iter()
+                .flat_map(|op| op.iter_changes(&old, &new))
+                .map(|change| {
+                    let value = change.value().trim_end_matches('\n').to_string();
+                    change_line(
+                        change.tag(),
+                        change.old_index(),
+                        change.new_index(),
+                        vec![(false, value)],
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// One line of a hunk, from its pieces and whether each is emphasized.
+fn change_line(
+    tag: ChangeTag,
+    old_index: Option<usize>,
+    new_index: Option<usize>,
+    pieces: Vec<(bool, String)>,
+) -> Line<'static> {
+    let (sign, style) = match tag {
+        ChangeTag::Delete => ("-", Style::new().fg(Color::Red)),
+        ChangeTag::Insert => ("+", Style::new().fg(Color::Green)),
+        ChangeTag::Equal => (" ", Style::new().add_modifier(Modifier::DIM)),
+    };
+    let number = |idx: Option<usize>| match idx {
+        Some(idx) => format!("{:<4}", idx + 1),
+        None => "    ".to_string(),
+    };
+    let mut spans = vec![
+        Span::styled(
+            format!("{}{} |", number(old_index), number(new_index)),
+            Style::new().add_modifier(Modifier::DIM),
+        ),
+        Span::styled(sign, style.add_modifier(Modifier::BOLD)),
+    ];
+    for (emphasized, value) in pieces {
+        if emphasized {
+            spans.push(Span::styled(
+                value,
+                style.add_modifier(Modifier::UNDERLINED),
+            ));
+        } else {
+            spans.push(Span::styled(value, style));
+        }
+    }
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{
+        header::{HeaderMap, HeaderValue},
+        StatusCode, Version,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+users:
+  req1:
+    url: http://localhost:8080/v1/users
+  req2:
+    url: http://localhost:8080/v2/users
+  res:
+    skip_headers: [date]
+"#;
+
+    fn response(date: &'static str, body: Value) -> ResponseData {
+        let mut headers = HeaderMap::new();
+        headers.insert("date", HeaderValue::from_static(date));
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        ResponseData {
+            status: StatusCode::OK,
+            version: Version::HTTP_11,
+            headers,
+            body: body.to_string(),
+            truncated: None,
+        }
+    }
+
+    fn users_app(path: PathBuf) -> App {
+        let config = DiffConfig::from_yaml(CONFIG).unwrap();
+        let args = DiffArgs::default();
+        let profile = config.get_profile("users").unwrap();
+        let mut cases: Vec<_> = profile
+            .cases("users")
+            .unwrap()
+            .into_iter()
+            .map(|case| {
+                let responses = Ok((
+                    response("Mon", json!({ "id": 1, "updated_at": "monday" })),
+                    response(
+                        "Tue",
+                        json!({ "id": 1, "updated_at": "tuesday", "etag": "x" }),
+                    ),
+                ));
+                TuiCase::new("users".to_string(), case, responses, &args)
+            })
+            .collect();
+        let failed = profile.cases("users").unwrap().remove(0);
+        cases.push(TuiCase::new(
+            "users".to_string(),
+            failed,
+            Err("connection refused".to_string()),
+            &args,
+        ));
+        App::new(config, path, cases, args)
+    }
+
+    fn select_rule(app: &mut App, rule: Rule) {
+        app.open_rules();
+        let idx = app.rules.iter().position(|r| *r == rule).unwrap();
+        app.rule_list.select(Some(idx));
+    }
+
+    #[test]
+    fn candidates_should_list_headers_then_body_fields_of_both_sides() {
+        let app = users_app(PathBuf::from("xdiff.yml"));
+        assert_eq!(
+            app.cases[0].candidates(),
+            [
+                Rule::Header("date".to_string()),
+                Rule::Header("content-type".to_string()),
+                Rule::Body("id".to_string()),
+                Rule::Body("updated_at".to_string()),
+                Rule::Body("etag".to_string()),
+            ]
+        );
+        assert!(app.cases[1].candidates().is_empty());
+    }
+
+    #[test]
+    fn failed_cases_should_rediff_as_errors() {
+        let mut app = users_app(PathBuf::from("xdiff.yml"));
+        assert!(matches!(app.cases[0].outcome, Outcome::Different { .. }));
+        assert_eq!(app.cases[0].hunks.len(), 1);
+        app.cases[1].rediff(&DiffArgs::default());
+        assert!(
+            matches!(&app.cases[1].outcome, Outcome::Error { error } if error == "connection refused")
+        );
+        assert!(app.cases[1].hunks.is_empty());
+    }
+
+    #[test]
+    fn toggled_rules_should_rediff_every_case_of_the_profile() {
+        let mut app = users_app(PathBuf::from("xdiff.yml"));
+        for field in ["updated_at", "etag"] {
+            select_rule(&mut app, Rule::Body(field.to_string()));
+            app.toggle_rule();
+        }
+        assert!(app.dirty);
+        assert!(matches!(app.cases[0].outcome, Outcome::Equal));
+        assert!(app.cases[0].hunks.is_empty());
+        let res = &app.config.get_profile("users").unwrap().res;
+        assert_eq!(res.skip_body, ["updated_at", "etag"]);
+        assert_eq!(app.cases[1].case.profile.res.skip_body, res.skip_body);
+
+        // Toggling a rule again removes it.
+        select_rule(&mut app, Rule::Header("date".to_string()));
+        app.toggle_rule();
+        assert!(app
+            .config
+            .get_profile("users")
+            .unwrap()
+            .res
+            .skip_headers
+            .is_empty());
+        assert!(matches!(app.cases[0].outcome, Outcome::Different { .. }));
+    }
+
+    #[tokio::test]
+    async fn saves_should_write_the_config_back_after_backing_it_up() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("xdiff.yml");
+        std::fs::write(&path, CONFIG)?;
+        let mut app = users_app(path.clone());
+        assert_eq!(app.backup_path(), dir.path().join("xdiff.yml.bak"));
+
+        select_rule(&mut app, Rule::Body("updated_at".to_string()));
+        app.toggle_rule();
+        app.save().await?;
+        assert!(!app.dirty);
+        let saved = DiffConfig::load(&path).await?;
+        assert_eq!(
+            saved.get_profile("users").unwrap().res.skip_body,
+            ["updated_at"]
+        );
+
+        // Later saves leave the backup of the original alone.
+        app.toggle_rule();
+        app.save().await?;
+        assert_eq!(std::fs::read_to_string(app.backup_path())?, CONFIG);
+
+        let mut app = users_app(dir.path().join("missing").join("xdiff.yml"));
+        assert!(app.save().await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt::{self, Write};
+
+use anyhow::Result;
+use console::{style, Style};
+use similar::{capture_diff_slices, group_diff_ops, ChangeTag, DiffTag, TextDiff};
+
+use crate::{DiffOptions, Granularity};
+
+struct Line(Option<usize>);
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            None => write!(f, "    "),
+            Some(idx) => write!(f, "{:<4}", idx + 1),
+        }
+    }
+}
+
+/// Render a diff of the two texts in the `terminal-inline` style, as configured by `options`.
+/// Returns an empty string when the texts are equal.
+pub fn diff_text(text1: &str, text2: &str, options: &DiffOptions) -> Result<String> {
+    match options.granularity {
+        Granularity::Line if !options.ignore_whitespace && !options.ignore_case => {
+            diff_lines(text1, text2, options)
+        }
+        Granularity::Line => diff_normalized_lines(text1, text2, options),
+        Granularity::Word | Granularity::Char => diff_tokens(text1, text2, options),
+    }
+}
+
+fn diff_lines(text1: &str, text2: &str, options: &DiffOptions) -> Result<String> {
+    let mut output = String::new();
+    let diff = TextDiff::configure()
+        .algorithm(options.algorithm.into())
+        .diff_lines(text1, text2);
+
+    for (idx, group) in diff.grouped_ops(options.context).iter().enumerate() {
+        if idx > 0 {
+            writeln!(&mut output, "{:-^1$}", "-", 80)?;
+        }
+        for op in group {
+            for change in diff.iter_inline_changes(op) {
+                let (sign, s) = match change.tag() {
+                    ChangeTag::Delete => ("-", Style::new().red()),
+                    ChangeTag::Insert => ("+", Style::new().green()),
+                    ChangeTag::Equal => (" ", Style::new().dim()),
+                };
+                write!(
+                    &mut output,
+                    "{}{} |{}",
+                    style(Line(change.old_index())).dim(),
+                    style(Line(change.new_index())).dim(),
+                    s.apply_to(sign).bold(),
+                )?;
+                for (emphasized, value) in change.iter_strings_lossy() {
+                    if emphasized {
+                        write!(&mut output, "{}", s.apply_to(value).underlined().on_black())?;
+                    } else {
+                        write!(&mut output, "{}", s.apply_to(value))?;
+                    }
+                }
+                if change.missing_newline() {
+                    writeln!(&mut output)?;
+                }
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// A line diff where lines are compared after applying the ignore options, but shown as is.
+fn diff_normalized_lines(text1: &str, text2: &str, options: &DiffOptions) -> Result<String> {
+    let old: Vec<&str> = text1.split_inclusive('\n').collect();
+    let new: Vec<&str> = text2.split_inclusive('\n').collect();
+    let old_keys: Vec<String> = old.iter().map(|l| options.normalize(l)).collect();
+    let new_keys: Vec<String> = new.iter().map(|l| options.normalize(l)).collect();
+    let ops = capture_diff_slices(options.algorithm.into(), &old_keys, &new_keys);
+    if ops.iter().all(|op| op.tag() == DiffTag::Equal) {
+        return Ok(String::new());
+    }
+
+    let mut output = String::new();
+    for (idx, group) in group_diff_ops(ops, options.context).iter().enumerate() {
+        if idx > 0 {
+            writeln!(&mut output, "{:-^1$}", "-", 80)?;
+        }
+        for op in group {
+            for change in op.iter_changes(&old, &new) {
+                let (sign, s) = match change.tag() {
+                    ChangeTag::Delete => ("-", Style::new().red()),
+                    ChangeTag::Insert => ("+", Style::new().green()),
+                    ChangeTag::Equal => (" ", Style::new().dim()),
+                };
+                let value = change.value();
+                write!(
+                    &mut output,
+                    "{}{} |{}{}",
+                    style(Line(change.old_index())).dim(),
+                    style(Line(change.new_index())).dim(),
+                    s.apply_to(sign).bold(),
+                    s.apply_to(value.trim_end_matches('\n')),
+                )?;
+                writeln!(&mut output)?;
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// A word or character diff. Changed lines are shown once, with removed text as `[-...-]` and
+/// added text as `{+...+}`.
+fn diff_tokens(text1: &str, text2: &str, options: &DiffOptions) -> Result<String> {
+    let old = tokenize(text1, options.granularity);
+    let new = tokenize(text2, options.granularity);
+    let old_keys: Vec<String> = old.iter().map(|t| options.normalize(t)).collect();
+    let new_keys: Vec<String> = new.iter().map(|t| options.normalize(t)).collect();
+    let ops = capture_diff_slices(options.algorithm.into(), &old_keys, &new_keys);
+    if ops.iter().all(|op| op.tag() == DiffTag::Equal) {
+        return Ok(String::new());
+    }
+
+    // Split the changes into output lines, remembering where each line starts on both sides.
+    let mut lines: Vec<TokenLine> = vec![TokenLine::new(0, 0)];
+    let (mut old_line, mut new_line) = (0, 0);
+    for op in &ops {
+        for change in op.iter_changes(&old, &new) {
+            let tag = change.tag();
+            for piece in change.value().split_inclusive('\n') {
+                let line = lines.last_mut().expect("there is always a current line");
+                let text = piece.trim_end_matches('\n');
+                match line.pieces.last_mut() {
+                    _ if text.is_empty() => {}
+                    Some((last, s)) if *last == tag => s.push_str(text),
+                    _ => line.pieces.push((tag, text.to_string())),
+                }
+                line.changed |= tag != ChangeTag::Equal;
+                if piece.ends_with('\n') {
+                    match tag {
+                        ChangeTag::Equal => {
+                            old_line += 1;
+                            new_line += 1;
+                        }
+                        ChangeTag::Delete => old_line += 1,
+                        ChangeTag::Insert => new_line += 1,
+                    }
+                    lines.push(TokenLine::new(old_line, new_line));
+                }
+            }
+        }
+    }
+
+    let mut visible = vec![false; lines.len()];
+    for (idx, line) in lines.iter().enumerate() {
+        if line.changed {
+            let start = idx.saturating_sub(options.context);
+            let end = (idx + options.context + 1).min(lines.len());
+            visible[start..end].iter_mut().for_each(|v| *v = true);
+        }
+    }
+
+    let mut output = String::new();
+    let mut last = None;
+    for (idx, line) in lines.iter().enumerate() {
+        if !visible[idx] || (line.pieces.is_empty() && idx == lines.len() - 1) {
+            continue;
+        }
+        if matches!(last, Some(l) if l + 1 != idx) {
+            writeln!(&mut output, "{:-^1$}", "-", 80)?;
+        }
+        last = Some(idx);
+        let sign = if line.changed { "~" } else { " " };
+        write!(
+            &mut output,
+            "{}{} |{}",
+            style(Line(Some(line.old))).dim(),
+            style(Line(Some(line.new))).dim(),
+            style(sign).yellow().bold(),
+        )?;
+        for (tag, text) in &line.pieces {
+            match tag {
+                ChangeTag::Equal => write!(&mut output, "{}", style(text).dim())?,
+                ChangeTag::Delete => {
+                    write!(&mut output, "{}", style(format!("[-{}-]", text)).red())?
+                }
+                ChangeTag::Insert => {
+                    write!(&mut output, "{}", style(format!("{{+{}+}}", text)).green())?
+                }
+            }
+        }
+        writeln!(&mut output)?;
+    }
+    Ok(output)
+}
+
+struct TokenLine {
+    old: usize,
+    new: usize,
+    pieces: Vec<(ChangeTag, String)>,
+    changed: bool,
+}
+
+impl TokenLine {
+    fn new(old: usize, new: usize) -> Self {
+        Self {
+            old,
+            new,
+            pieces: Vec::new(),
+            changed: false,
+        }
+    }
+}
+
+/// Split text into words (runs of alphanumerics), whitespace runs and single punctuation
+/// characters, or into single characters.
+pub(crate) fn tokenize(text: &str, granularity: Granularity) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut kind = None;
+    for (idx, c) in text.char_indices() {
+        let class = match granularity {
+            Granularity::Char => None,
+            _ if c == '\n' => None,
+            _ if c.is_alphanumeric() || c == '_' => Some(0),
+            _ if c.is_whitespace() => Some(1),
+            _ => None,
+        };
+        if idx > start && (class.is_none() || class != kind) {
+            tokens.push(&text[start..idx]);
+            start = idx;
+        }
+        kind = class;
+        if class.is_none() {
+            let end = idx + c.len_utf8();
+            tokens.push(&text[start..end]);
+            start = end;
+        }
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// Escape text for use in XML or HTML content and attribute values.
+pub(crate) fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
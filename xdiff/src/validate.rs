@@ -0,0 +1,687 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use regex::Regex;
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    Method, Url,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use toml::de::{DeTable, DeValue};
+use yaml_rust2::{
+    parser::{MarkedEventReceiver, Parser},
+    scanner::Marker,
+    Event,
+};
+
+use crate::{
+    config::ConfigFormat, jsonpath::JsonPath, matrix::MatrixProfile, script::Script,
+    transform::Transform, Assertion, DiffConfig, LimitConfig, Limiter, RedactConfig,
+    RequestProfile, ResponseData, ResponseProfile,
+};
+
+/// The standard methods, to catch them spelled in lowercase.
+const METHODS: &[&str] = &[
+    "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+type Pointer = Vec<Segment>;
+
+/// A problem found in a config file.
+#[derive(Debug, Clone)]
+pub struct Problem {
+    /// Line and column, both starting at 1, when known.
+    pub position: Option<(usize, usize)>,
+    /// Where in the config the problem is, e.g. `todo.req1.method`.
+    pub path: String,
+    pub message: String,
+}
+
+/// Checks a config file and collects every problem found, rather than stopping at the first
+/// one like loading it does.
+pub struct Validation {
+    /// The directory of the config file, that its relative paths are relative to.
+    base: PathBuf,
+    positions: HashMap<Pointer, (usize, usize)>,
+    problems: Vec<(Pointer, String)>,
+    syntax: Option<Problem>,
+}
+
+impl Validation {
+    /// Parse `content`, in the format given by the extension of `path`, and check it.
+    pub fn new(path: &Path, content: &str) -> Self {
+        let mut validation = Self {
+            base: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            positions: HashMap::new(),
+            problems: Vec::new(),
+            syntax: None,
+        };
+        let parsed = match ConfigFormat::of(path) {
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| {
+                let position = e.location().map(|l| (l.line(), l.column()));
+                (position, e.to_string())
+            }),
+            ConfigFormat::Json => serde_json::from_str(content)
+                .map_err(|e| (Some((e.line(), e.column())), e.to_string())),
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| {
+                let position = e.span().map(|s| line_col(content, s.start));
+                (position, e.message().to_string())
+            }),
+        };
+        match parsed {
+            Ok(value) => {
+                validation.positions = match ConfigFormat::of(path) {
+                    ConfigFormat::Toml => toml_positions(content),
+                    _ => yaml_positions(content),
+                };
+                validation.check(&value);
+            }
+            Err((position, message)) => {
+                validation.syntax = Some(Problem {
+                    position,
+                    path: String::new(),
+                    message,
+                })
+            }
+        }
+        validation
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.syntax.is_none() && self.problems.is_empty()
+    }
+
+    /// Report a problem with a profile as a whole.
+    pub fn report(&mut self, profile: &str, message: impl Into<String>) {
+        self.push(vec![Segment::Key(profile.to_string())], message);
+    }
+
+    /// Report the skip rules of a profile that match nothing in any of the given responses.
+    pub fn check_skip_rules(
+        &mut self,
+        profile: &str,
+        res: &ResponseProfile,
+        responses: &[(ResponseData, ResponseData)],
+    ) -> Result<()> {
+        if responses.is_empty() {
+            return Ok(());
+        }
+        for (field, idx) in res.unused_skip_rules(responses)? {
+            let rule = match field {
+                "skip_headers" => &res.skip_headers[idx],
+                _ => &res.skip_body[idx],
+            };
+            self.push(
+                pointer(profile, &["res", field]).with(Segment::Index(idx)),
+                format!("`{}` matches nothing in any response", rule),
+            );
+        }
+        Ok(())
+    }
+
+    /// Every problem found, in the order they appear in the file.
+    pub fn into_problems(self) -> Vec<Problem> {
+        if let Some(problem) = self.syntax {
+            return vec![problem];
+        }
+        let mut problems: Vec<_> = self
+            .problems
+            .iter()
+            .map(|(pointer, message)| Problem {
+                position: self.position(pointer),
+                path: display_pointer(pointer),
+                message: message.clone(),
+            })
+            .collect();
+        problems.sort_by_key(|p| p.position.unwrap_or((usize::MAX, 0)));
+        problems
+    }
+
+    fn check(&mut self, value: &Value) {
+        let profiles = match value.as_object() {
+            Some(profiles) => profiles,
+            None => {
+                self.push(
+                    Vec::new(),
+                    "expected a mapping of profile names to profiles",
+                );
+                return;
+            }
+        };
+        let schema = serde_json::to_value(schemars::schema_for!(DiffConfig))
+            .expect("the config schema is valid JSON");
+        self.check_keys(&schema, &schema, value, &mut Vec::new());
+        for (name, value) in profiles {
+            if name == "redact" {
+                self.check_redact(value);
+            } else if name == "limits" {
+                self.check_limits(value);
+            } else {
+                self.check_profile(name, value);
+            }
+        }
+    }
+
+    /// Report keys that the config schema doesn't know about, which serde silently ignores.
+    fn check_keys(&mut self, root: &Value, schema: &Value, value: &Value, at: &mut Pointer) {
+        let schema = resolve(root, schema);
+        if let Some(variants) = schema.get("anyOf").or_else(|| schema.get("oneOf")) {
+            let variant = variants
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|v| resolve(root, v))
+                .find(|v| accepts_type(v, value));
+            if let Some(variant) = variant {
+                self.check_keys(root, variant, value, at);
+            }
+            return;
+        }
+        match value {
+            Value::Object(map) => {
+                let properties = schema.get("properties").and_then(Value::as_object);
+                let additional = schema.get("additionalProperties");
+                for (key, value) in map {
+                    at.push(Segment::Key(key.clone()));
+                    match (properties.and_then(|p| p.get(key)), additional) {
+                        (Some(schema), _) => self.check_keys(root, schema, value, at),
+                        (None, Some(schema)) if schema.is_object() => {
+                            self.check_keys(root, schema, value, at)
+                        }
+                        (None, Some(Value::Bool(false))) | (None, None) if properties.is_some() => {
+                            self.push(at.clone(), format!("unknown key `{}`", key))
+                        }
+                        _ => {}
+                    }
+                    at.pop();
+                }
+            }
+            Value::Array(items) => {
+                if let Some(schema) = schema.get("items") {
+                    for (idx, item) in items.iter().enumerate() {
+                        at.push(Segment::Index(idx));
+                        self.check_keys(root, schema, item, at);
+                        at.pop();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_redact(&mut self, value: &Value) {
+        let at = vec![Segment::Key("redact".into())];
+        if let Some(config) = self.deserialize::<RedactConfig>(&at, value) {
+            for (idx, pattern) in config.patterns.iter().enumerate() {
+                if let Err(e) = Regex::new(pattern) {
+                    self.push(
+                        at.with(Segment::Key("patterns".into()))
+                            .with(Segment::Index(idx)),
+                        format!("invalid pattern: {}", e),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_limits(&mut self, value: &Value) {
+        let at = vec![Segment::Key("limits".into())];
+        if let Some(config) = self.deserialize::<LimitConfig>(&at, value) {
+            if let Err(e) = Limiter::new(&config) {
+                self.push(at.with(Segment::Key("rps".into())), e.to_string());
+            }
+            for (key, limit) in [
+                ("per_host", config.per_host),
+                ("concurrency", config.concurrency),
+            ] {
+                if limit == Some(0) {
+                    self.push(at.with(Segment::Key(key.into())), "must be at least 1");
+                }
+            }
+        }
+    }
+
+    fn check_profile(&mut self, name: &str, value: &Value) {
+        let map = match value.as_object() {
+            Some(map) => map,
+            None => {
+                self.report(name, "expected a profile with req1, req2 and res");
+                return;
+            }
+        };
+        for field in ["req1", "req2", "res"] {
+            match map.get(field) {
+                None => self.report(name, format!("missing `{}`", field)),
+                Some(value) if field == "res" => self.check_response(name, value),
+                Some(value) => self.check_request(pointer(name, &[field]), value),
+            }
+        }
+        if let Some(value) = map.get("matrix") {
+            let at = pointer(name, &["matrix"]);
+            if let Some(mut matrix) = self.deserialize::<MatrixProfile>(&at, value) {
+                matrix.base = Some(self.base.clone());
+                if let Err(e) = matrix.combinations() {
+                    self.push(at, format!("{:#}", e));
+                }
+            }
+        }
+    }
+
+    fn check_request(&mut self, at: Pointer, value: &Value) {
+        let before = self.problems.len();
+        if let Some(method) = value.get("method").and_then(Value::as_str) {
+            let upper = method.to_ascii_uppercase();
+            if Method::from_bytes(method.as_bytes()).is_err() {
+                self.push(
+                    at.with(Segment::Key("method".into())),
+                    format!("invalid method `{}`", method),
+                );
+            } else if method != upper && METHODS.contains(&upper.as_str()) {
+                // Any token is a valid extension method, but a lowercase standard method is
+                // sent as is and most likely rejected.
+                self.push(
+                    at.with(Segment::Key("method".into())),
+                    format!(
+                        "unknown method `{}`, methods are case-sensitive: use `{}`",
+                        method, upper
+                    ),
+                );
+            }
+        }
+        if let Some(url) = value.get("url").and_then(Value::as_str) {
+            if let Err(e) = Url::parse(url) {
+                self.push(
+                    at.with(Segment::Key("url".into())),
+                    format!("invalid url `{}`: {}", url, e),
+                );
+            }
+        }
+        if let Some(headers) = value.get("headers").and_then(Value::as_object) {
+            for (name, v) in headers {
+                let header = at
+                    .with(Segment::Key("headers".into()))
+                    .with(Segment::Key(name.clone()));
+                if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                    self.push(header, format!("invalid header name `{}`", name));
+                } else if let Some(Err(_)) = v.as_str().map(HeaderValue::from_str) {
+                    self.push(header, format!("invalid value for header `{}`", name));
+                }
+            }
+        }
+        let kinds: Vec<_> = ["body", "graphql", "grpc", "websocket"]
+            .into_iter()
+            .filter(|k| value.get(k).is_some())
+            .collect();
+        if kinds.len() > 1 {
+            self.push(
+                at.with(Segment::Key(kinds[1].into())),
+                format!("{} can't be used together", kinds.join(" and ")),
+            );
+        }
+        if let Some(grpc) = value.get("grpc").and_then(Value::as_object) {
+            if grpc.contains_key("proto") == grpc.contains_key("descriptor_set") {
+                self.push(
+                    at.with(Segment::Key("grpc".into())),
+                    "grpc needs exactly one of proto and descriptor_set",
+                );
+            }
+        }
+        if let Some(graphql) = value.get("graphql").and_then(Value::as_object) {
+            let at = at.with(Segment::Key("graphql".into()));
+            if graphql.contains_key("query") == graphql.contains_key("query_file") {
+                self.push(at, "graphql needs exactly one of query and query_file");
+            }
+        }
+        if let Some(signing) = value.get("signing").and_then(Value::as_object) {
+            let at = at.with(Segment::Key("signing".into()));
+            if signing.contains_key("hmac") == signing.contains_key("aws_sigv4") {
+                self.push(at, "signing needs exactly one of hmac and aws_sigv4");
+            } else if let Some(kind) = ["grpc", "websocket"]
+                .iter()
+                .find(|k| value.get(k).is_some())
+            {
+                self.push(at, format!("{} requests can't be signed", kind));
+            }
+        }
+        if value.get("http_version").is_some() {
+            if let Some(kind) = ["grpc", "websocket"]
+                .iter()
+                .find(|k| value.get(k).is_some())
+            {
+                self.push(
+                    at.with(Segment::Key("http_version".into())),
+                    format!("{} requests don't take an http_version", kind),
+                );
+            }
+        }
+        if let Some(script) = value.get("script").and_then(Value::as_object) {
+            for hook in ["pre", "post"] {
+                if let Some(Err(e)) = script.get(hook).and_then(Value::as_str).map(Script::new) {
+                    self.push(
+                        at.with(Segment::Key("script".into()))
+                            .with(Segment::Key(hook.into())),
+                        format!("{:#}", e),
+                    );
+                }
+            }
+        }
+        // The checks above are more precise than serde's first error, so only fall back to it
+        // when they found nothing.
+        if self.problems.len() == before {
+            self.deserialize::<RequestProfile>(&at, value);
+        }
+    }
+
+    fn check_response(&mut self, name: &str, value: &Value) {
+        let at = pointer(name, &["res"]);
+        // Assertions are read one by one, so that a broken one hides neither the others nor
+        // the rest of the section.
+        let mut value = value.clone();
+        let assertions = value.as_object_mut().and_then(|res| res.remove("assert"));
+        let res = match self.deserialize::<ResponseProfile>(&at, &value) {
+            Some(res) => res,
+            None => return,
+        };
+        let at = pointer(name, &["res", "assert"]);
+        let mut assert = Vec::new();
+        match &assertions {
+            Some(Value::Array(items)) => {
+                for (idx, item) in items.iter().enumerate() {
+                    let at = at.with(Segment::Index(idx));
+                    if let Some(assertion) = self.deserialize::<Assertion>(&at, item) {
+                        assert.push((at, assertion));
+                    }
+                }
+            }
+            Some(other) => {
+                self.deserialize::<Vec<Assertion>>(&at, other);
+            }
+            None => {}
+        }
+        for (idx, skip) in res.skip_body.iter().enumerate() {
+            if let Some(Err(e)) = skip.starts_with('$').then(|| skip.parse::<JsonPath>()) {
+                self.push(
+                    pointer(name, &["res", "skip_body"]).with(Segment::Index(idx)),
+                    format!("{:#}", e),
+                );
+            }
+        }
+        for (side, code) in [("req1", &res.transform.req1), ("req2", &res.transform.req2)] {
+            if let Some(Err(e)) = code.as_deref().map(Transform::new) {
+                self.push(
+                    pointer(name, &["res", "transform", side]),
+                    format!("{:#}", e),
+                );
+            }
+        }
+        for (idx, array) in res.unordered_arrays.iter().enumerate() {
+            if let Err(e) = array.path().parse::<JsonPath>() {
+                self.push(
+                    pointer(name, &["res", "unordered_arrays"]).with(Segment::Index(idx)),
+                    format!("{:#}", e),
+                );
+            }
+        }
+        for (at, assertion) in assert {
+            if res.stream && assertion.reads_body() {
+                self.push(at, "body assertions are skipped when `stream` is set");
+            }
+        }
+    }
+
+    /// Deserialize one section, reporting serde's error at the path where it occurred.
+    fn deserialize<T: DeserializeOwned>(&mut self, at: &Pointer, value: &Value) -> Option<T> {
+        match serde_path_to_error::deserialize(value.clone()) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                let mut at = at.clone();
+                for segment in e.path().iter() {
+                    match segment {
+                        serde_path_to_error::Segment::Seq { index } => {
+                            at.push(Segment::Index(*index))
+                        }
+                        serde_path_to_error::Segment::Map { key } => {
+                            at.push(Segment::Key(key.clone()))
+                        }
+                        _ => {}
+                    }
+                }
+                self.push(at, e.into_inner().to_string());
+                None
+            }
+        }
+    }
+
+    fn push(&mut self, at: Pointer, message: impl Into<String>) {
+        self.problems.push((at, message.into()));
+    }
+
+    /// The position of `pointer`, or of its closest ancestor found in the file.
+    fn position(&self, pointer: &[Segment]) -> Option<(usize, usize)> {
+        (0..=pointer.len())
+            .rev()
+            .find_map(|len| self.positions.get(&pointer[..len]))
+            .copied()
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((line, col)) = self.position {
+            write!(f, "{}:{}: ", line, col)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+trait With {
+    fn with(&self, segment: Segment) -> Self;
+}
+
+impl With for Pointer {
+    fn with(&self, segment: Segment) -> Self {
+        let mut pointer = self.clone();
+        pointer.push(segment);
+        pointer
+    }
+}
+
+fn pointer(profile: &str, keys: &[&str]) -> Pointer {
+    std::iter::once(profile)
+        .chain(keys.iter().copied())
+        .map(|k| Segment::Key(k.to_string()))
+        .collect()
+}
+
+fn display_pointer(pointer: &[Segment]) -> String {
+    let mut s = String::new();
+    for segment in pointer {
+        match segment {
+            Segment::Key(key) if s.is_empty() => s.push_str(key),
+            Segment::Key(key) => {
+                s.push('.');
+                s.push_str(key);
+            }
+            Segment::Index(idx) => s.push_str(&format!("[{}]", idx)),
+        }
+    }
+    s
+}
+
+/// Follow a local `$ref` to its definition.
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema.get("$ref").and_then(Value::as_str) {
+        Some(r) => r
+            .strip_prefix('#')
+            .and_then(|p| root.pointer(p))
+            .unwrap_or(schema),
+        None => schema,
+    }
+}
+
+fn accepts_type(schema: &Value, value: &Value) -> bool {
+    let name = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    let accepts = |t: &Value| t == name || (t == "number" && name == "integer");
+    match schema.get("type") {
+        Some(Value::Array(types)) => types.iter().any(accepts),
+        Some(t) => accepts(t),
+        None => true,
+    }
+}
+
+fn line_col(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let col = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, col)
+}
+
+/// Positions of every key and array item of a YAML (or JSON) document.
+fn yaml_positions(content: &str) -> HashMap<Pointer, (usize, usize)> {
+    let mut locator = YamlLocator::default();
+    // The document already parsed, so errors here can only leave some positions unknown.
+    let _ = Parser::new_from_str(content).load(&mut locator, false);
+    locator.positions
+}
+
+fn marker_position(mark: Marker) -> (usize, usize) {
+    // Marker lines start at 1 but columns at 0.
+    (mark.line(), mark.col() + 1)
+}
+
+#[derive(Default)]
+struct YamlLocator {
+    /// For each open mapping, the key whose value is being read; for each sequence, the index
+    /// of the current item.
+    frames: Vec<Frame>,
+    pointer: Pointer,
+    positions: HashMap<Pointer, (usize, usize)>,
+}
+
+enum Frame {
+    Mapping(Option<String>),
+    Sequence(usize),
+}
+
+impl YamlLocator {
+    fn enter(&mut self, mark: Marker) {
+        match self.frames.last() {
+            Some(Frame::Mapping(Some(key))) => self.pointer.push(Segment::Key(key.clone())),
+            Some(Frame::Mapping(None)) => self.pointer.push(Segment::Key(String::new())),
+            Some(Frame::Sequence(idx)) => {
+                self.pointer.push(Segment::Index(*idx));
+                self.positions
+                    .insert(self.pointer.clone(), marker_position(mark));
+            }
+            None => {}
+        }
+    }
+
+    fn leave(&mut self) {
+        match self.frames.last_mut() {
+            Some(Frame::Mapping(key)) => *key = None,
+            Some(Frame::Sequence(idx)) => *idx += 1,
+            None => return,
+        }
+        self.pointer.pop();
+    }
+}
+
+impl MarkedEventReceiver for YamlLocator {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(key, ..) if matches!(self.frames.last(), Some(Frame::Mapping(None))) => {
+                self.positions.insert(
+                    self.pointer.with(Segment::Key(key.clone())),
+                    marker_position(mark),
+                );
+                if let Some(frame) = self.frames.last_mut() {
+                    *frame = Frame::Mapping(Some(key));
+                }
+            }
+            Event::Scalar(..) | Event::Alias(_) => {
+                self.enter(mark);
+                self.leave();
+            }
+            Event::MappingStart(..) => {
+                self.enter(mark);
+                self.frames.push(Frame::Mapping(None));
+            }
+            Event::SequenceStart(..) => {
+                self.enter(mark);
+                self.frames.push(Frame::Sequence(0));
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.frames.pop();
+                self.leave();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Positions of every key and array item of a TOML document.
+fn toml_positions(content: &str) -> HashMap<Pointer, (usize, usize)> {
+    let mut positions = HashMap::new();
+    if let Ok(table) = DeTable::parse(content) {
+        for (key, value) in table.get_ref() {
+            let pointer = vec![Segment::Key(key.get_ref().to_string())];
+            positions.insert(pointer.clone(), line_col(content, key.span().start));
+            toml_walk(content, value.get_ref(), pointer, &mut positions);
+        }
+    }
+    positions
+}
+
+fn toml_walk(
+    content: &str,
+    value: &DeValue,
+    pointer: Pointer,
+    positions: &mut HashMap<Pointer, (usize, usize)>,
+) {
+    match value {
+        DeValue::Table(table) => {
+            for (key, value) in table {
+                let pointer = pointer.with(Segment::Key(key.get_ref().to_string()));
+                positions.insert(pointer.clone(), line_col(content, key.span().start));
+                toml_walk(content, value.get_ref(), pointer, positions);
+            }
+        }
+        DeValue::Array(items) => {
+            for (idx, item) in items.into_iter().enumerate() {
+                let pointer = pointer.with(Segment::Index(idx));
+                positions.insert(pointer.clone(), line_col(content, item.span().start));
+                toml_walk(content, item.get_ref(), pointer, positions);
+            }
+        }
+        _ => {}
+    }
+}
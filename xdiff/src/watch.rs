@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use serde::Serialize;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, time};
+
+use crate::{DiffArgs, DiffCase, Outcome, Redactor};
+
+#[derive(Debug, Serialize)]
+struct WatchRecord<'a> {
+    timestamp: String,
+    profile: &'a str,
+    changed: bool,
+    #[serde(flatten)]
+    outcome: &'a Outcome,
+}
+
+/// Periodically diff the cases of a profile, printing only transitions between states.
+pub struct Watcher<'a> {
+    pub cases: &'a [DiffCase],
+    pub interval: Duration,
+    pub log: Option<PathBuf>,
+    pub redactor: &'a Redactor,
+    pub args: &'a DiffArgs,
+}
+
+impl Watcher<'_> {
+    /// Run until interrupted with ctrl-c.
+    pub async fn run(&self, out: &mut impl Write) -> Result<()> {
+        let mut ticker = time::interval(self.interval);
+        ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        let mut last = HashMap::new();
+        // One listener for the whole run, so that a ctrl-c during a check isn't lost.
+        let ctrl_c = tokio::signal::ctrl_c();
+        tokio::pin!(ctrl_c);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = &mut ctrl_c => return Ok(()),
+            }
+            tokio::select! {
+                result = self.poll(&mut last, out) => result?,
+                _ = &mut ctrl_c => return Ok(()),
+            }
+        }
+    }
+
+    /// Diff every case once, reporting the ones whose outcome changed since `last` and logging
+    /// all of them. `last` holds the outcome of each case by name and is updated.
+    pub async fn poll(
+        &self,
+        last: &mut HashMap<String, Outcome>,
+        out: &mut impl Write,
+    ) -> Result<()> {
+        for case in self.cases {
+            let outcome = self.check(case).await;
+            let changed = last.get(&case.name) != Some(&outcome);
+            let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+            if changed {
+                self.report(out, &timestamp, &case.name, &outcome)?;
+            }
+            if let Some(path) = &self.log {
+                let record = WatchRecord {
+                    timestamp,
+                    profile: &case.name,
+                    changed,
+                    outcome: &outcome.plain(),
+                };
+                let mut line = serde_json::to_string(&record)?;
+                line.push('\n');
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(line.as_bytes()).await?;
+                // Tokio finishes writes in the background, so they could still be pending here.
+                file.flush().await?;
+            }
+            last.insert(case.name.clone(), outcome);
+        }
+        Ok(())
+    }
+
+    async fn check(&self, case: &DiffCase) -> Outcome {
+        let diff = async {
+            let (mut res1, mut res2) = case.profile.fetch(self.args).await?;
+            self.redactor.redact_response(&mut res1);
+            self.redactor.redact_response(&mut res2);
+            case.profile.diff_responses(self.args, &res1, &res2)
+        };
+        self.redactor.redact_outcome(Outcome::from_diff(diff.await))
+    }
+
+    fn report(
+        &self,
+        out: &mut impl Write,
+        timestamp: &str,
+        name: &str,
+        outcome: &Outcome,
+    ) -> Result<()> {
+        match outcome {
+            Outcome::Equal => writeln!(out, "[{}] {}: responses are equal", timestamp, name)?,
+            Outcome::Different { diff } => {
+                writeln!(out, "[{}] {}: responses differ", timestamp, name)?;
+                write!(out, "{}", diff)?;
+            }
+            Outcome::Error { error } => {
+                writeln!(out, "[{}] {}: error: {}", timestamp, name, error)?
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
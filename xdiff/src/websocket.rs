@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::{
+    header::{self, HeaderMap, HeaderValue},
+    StatusCode, Url, Version,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::time;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{
+        client::IntoClientRequest,
+        http::{HeaderName as WsHeaderName, HeaderValue as WsHeaderValue},
+        Message,
+    },
+};
+
+use crate::{req::header_map, ResponseData};
+
+/// A scripted WebSocket conversation: the frames in `send` are sent in order, then frames are
+/// received until `expect` of them arrived, the server closed, none arrived for `timeout`, or
+/// `deadline` passed.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct WebsocketProfile {
+    /// Text frames to send. Anything but a string is sent as its JSON text.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub send: Vec<Value>,
+    /// Stop after receiving this many frames.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub expect: Option<usize>,
+    /// Stop when no frame arrived for this long, e.g. `500ms`.
+    #[serde(with = "humantime_serde", default = "default_timeout")]
+    #[schemars(with = "String")]
+    pub timeout: Duration,
+    /// Stop when receiving took this long in total, e.g. `10s`, so that a server that keeps
+    /// sending frames can't keep the case running.
+    #[serde(with = "humantime_serde", default = "default_deadline")]
+    #[schemars(with = "String")]
+    pub deadline: Duration,
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_deadline() -> Duration {
+    Duration::from_secs(10)
+}
+
+impl WebsocketProfile {
+    /// Run the conversation with the server at `url`. The handshake response provides the status
+    /// and headers, and the transcript of received frames the body: a JSON array where frames
+    /// holding JSON are embedded as such, so that skip rules can reach into them.
+    pub(crate) async fn send(&self, url: &Url, headers: &HeaderMap) -> Result<ResponseData> {
+        let mut request = url.as_str().into_client_request()?;
+        for (name, value) in headers {
+            request.headers_mut().append(
+                WsHeaderName::from_bytes(name.as_str().as_bytes())?,
+                WsHeaderValue::from_bytes(value.as_bytes())?,
+            );
+        }
+        let (mut socket, response) = connect_async(request)
+            .await
+            .with_context(|| format!("failed to connect to {}", url))?;
+
+        for frame in &self.send {
+            let text = match frame {
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            };
+            socket.send(Message::text(text)).await?;
+        }
+
+        let mut transcript = Vec::new();
+        let deadline = time::Instant::now() + self.deadline;
+        while self.expect.is_none_or(|n| transcript.len() < n) {
+            let until = deadline.min(time::Instant::now() + self.timeout);
+            let message = match time::timeout_at(until, socket.next()).await {
+                Ok(Some(message)) => message?,
+                Ok(None) | Err(_) => break,
+            };
+            match message {
+                Message::Text(text) => transcript.push(
+                    serde_json::from_str(&text).unwrap_or_else(|_| Value::String(text.to_string())),
+                ),
+                Message::Binary(data) => transcript.push(json!({ "binary_len": data.len() })),
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        // The transcript is complete either way, a failed close doesn't change it.
+        let _ = socket.close(None).await;
+
+        let mut headers = header_map(
+            response
+                .headers()
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_bytes())),
+        )?;
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        Ok(ResponseData {
+            status: StatusCode::from_u16(response.status().as_u16())?,
+            version: Version::HTTP_11,
+            headers,
+            body: serde_json::to_string(&transcript)?,
+            truncated: None,
+        })
+    }
+}
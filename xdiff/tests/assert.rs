@@ -0,0 +1,132 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::{json, Value};
+use tokio::process::Command;
+use xdiff::{
+    mock::{self, MockRoutes},
+    DiffArgs, DiffConfig,
+};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+
+#[tokio::test]
+async fn assertions_should_be_checked_on_each_side() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(
+            Method::GET,
+            "/v1/items",
+            mock::json(json!({ "items": [1, 2] })),
+        )
+        .respond(
+            Method::GET,
+            "/v2/items",
+            mock::json(json!({ "items": ["x"] })),
+        )
+        .start()
+        .await?;
+    let yaml = format!(
+        r#"
+items:
+  req1:
+    url: {url}/v1/items
+  req2:
+    url: {url}/v2/items
+  res:
+    assert:
+      - status == 200
+      - version == HTTP/1.1
+      - $.items.length > 1
+      - $.items[*] != 2
+      - schema: {FIXTURES}/items.schema.json
+"#,
+        url = server.url()
+    );
+    let config = DiffConfig::from_yaml(&yaml)?;
+    let profile = config.get_profile("items").unwrap();
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+
+    let violations = profile.res.violations(&res1, &res2).await?;
+    assert_eq!(violations.len(), 3, "{:?}", violations);
+    assert_eq!(violations[0], "req1: $.items[*] != 2: got 2");
+    assert_eq!(violations[1], "req2: $.items.length > 1: got 1");
+    assert!(violations[2].starts_with("req2: body at /items/0 doesn't match schema"));
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("xdiff.yml");
+    std::fs::write(&path, &yaml)?;
+    let output = Command::new(env!("CARGO_BIN_EXE_xdiff"))
+        .args(["run", "-c"])
+        .arg(&path)
+        .args(["-f", "json"])
+        .output()
+        .await?;
+    let report: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(report["cases"][0]["status"], "different");
+    assert_eq!(
+        report["cases"][0]["violations"].as_array().unwrap().len(),
+        3
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn schemas_should_be_read_once_from_the_config_directory() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let schema = dir.path().join("items.schema.json");
+    std::fs::copy(format!("{FIXTURES}/items.schema.json"), &schema)?;
+    let path = dir.path().join("xdiff.yml");
+    std::fs::write(
+        &path,
+        r#"
+items:
+  req1:
+    url: http://localhost/v1/items
+  req2:
+    url: http://localhost/v2/items
+  res:
+    assert:
+      - schema: items.schema.json
+"#,
+    )?;
+    let config = DiffConfig::load(&path).await?;
+    let profile = config.get_profile("items").unwrap();
+    let (res1, res2) = (
+        mock::json(json!({ "items": [1] })),
+        mock::json(json!({ "items": ["x"] })),
+    );
+    let violations = profile.res.violations(&res1, &res2).await?;
+    assert_eq!(violations.len(), 1, "{:?}", violations);
+    assert!(
+        violations[0].starts_with("req2: body at /items/0 doesn't match schema items.schema.json")
+    );
+
+    // The compiled schema is kept, also by the cases cloned from the profile.
+    std::fs::remove_file(&schema)?;
+    let case = profile.cases("items")?.remove(0);
+    assert_eq!(case.profile.res.violations(&res1, &res2).await?, violations);
+    Ok(())
+}
+
+#[test]
+fn expressions_should_be_parsed_with_the_config() -> Result<()> {
+    let config = |assert: &str| {
+        DiffConfig::from_yaml(&format!(
+            "items:\n  req1:\n    url: http://localhost/a\n  req2:\n    url: http://localhost/b\n  res:\n    assert: [{}]\n",
+            assert
+        ))
+    };
+    let error = config("'$.items =='").unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("assertion `$.items ==` has no value to compare with"),
+        "{}",
+        error
+    );
+
+    // Expressions are saved as written.
+    let yaml = config("'version == HTTP/1.1', '$.items.length >= 1'")?.to_yaml()?;
+    assert!(yaml.contains("- version == HTTP/1.1\n"), "{}", yaml);
+    assert!(yaml.contains("- $.items.length >= 1\n"), "{}", yaml);
+    Ok(())
+}
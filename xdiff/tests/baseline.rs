@@ -0,0 +1,90 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::{json, Value};
+use tokio::process::Command;
+use xdiff::{
+    mock::{self, MockRoutes},
+    Baseline,
+};
+
+#[tokio::test]
+async fn accepted_differences_should_pass_until_they_expire() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(
+            Method::GET,
+            "/v1/users/1",
+            mock::json(json!({ "id": 1, "name": "Ada", "role": "admin" })),
+        )
+        .respond(
+            Method::GET,
+            "/v2/users/1",
+            mock::json(json!({ "id": 1, "name": "Ada Lovelace", "role": "admin" })),
+        )
+        .start()
+        .await?;
+    let dir = tempfile::tempdir()?;
+    let (config, baseline) = (
+        dir.path().join("xdiff.yml"),
+        dir.path().join("baseline.yml"),
+    );
+    std::fs::write(
+        &config,
+        format!(
+            "users:\n  req1:\n    url: {url}/v1/users/1\n  req2:\n    url: {url}/v2/users/1\n  res:\n    skip_headers: [date, content-length]\n",
+            url = server.url()
+        ),
+    )?;
+    let xdiff = |args: &[&str]| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_xdiff"));
+        command.args(args).arg("-c").arg(&config);
+        async move {
+            let output = command.output().await?;
+            anyhow::Ok((output.status.code(), String::from_utf8(output.stdout)?))
+        }
+    };
+    let run = || async {
+        let baseline = baseline.to_str().unwrap();
+        let (code, report) = xdiff(&["run", "-f", "json", "-b", baseline]).await?;
+        let case = serde_json::from_str::<Value>(&report)?["cases"][0].clone();
+        anyhow::Ok((code, case))
+    };
+
+    let (code, case) = run().await?;
+    assert_eq!(case["status"], "different");
+    assert_eq!(code, Some(1));
+    let (code, _) = xdiff(&[
+        "accept",
+        "-b",
+        baseline.to_str().unwrap(),
+        "--expires",
+        "2999-01-01",
+        "-n",
+        "renamed in v2",
+    ])
+    .await?;
+    assert_eq!(code, Some(0));
+    let accepted = Baseline::load(&baseline).await?;
+    assert_eq!(
+        accepted.cases["users"][0].hunk,
+        "-  \"name\": \"Ada\",\n+  \"name\": \"Ada Lovelace\","
+    );
+    let (code, case) = run().await?;
+    assert_eq!(case["status"], "equal");
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        case["notes"][0],
+        "accepted difference until 2999-01-01: renamed in v2"
+    );
+
+    let mut expired = accepted.clone();
+    expired.cases.get_mut("users").unwrap()[0].expires = Some("2000-01-01".into());
+    expired.save(&baseline).await?;
+    let (code, case) = run().await?;
+    assert_eq!(case["status"], "different");
+    assert_eq!(code, Some(1));
+    assert_eq!(
+        case["notes"][0],
+        "accepted difference expired on 2000-01-01: renamed in v2"
+    );
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use reqwest::{Method, StatusCode};
+use serde_json::json;
+use xdiff::{
+    mock::{self, MockRoutes, MockServer},
+    Bench, DiffArgs, DiffConfig, Limiter,
+};
+
+/// A server answering with `{"n": 0}`, or, if `flaky`, with `{"n": 1}`, `{"n": 0}`,
+/// `{"n": 1}` and a 500 in turn.
+async fn serve(flaky: bool) -> Result<MockServer> {
+    let n = |n: u8| mock::json(json!({ "n": n }));
+    let routes = match flaky {
+        false => MockRoutes::new().respond(Method::GET, "/", n(0)),
+        true => [
+            n(1),
+            n(0),
+            n(1),
+            n(0).with_status(StatusCode::INTERNAL_SERVER_ERROR),
+        ]
+        .into_iter()
+        .fold(MockRoutes::new(), |routes, res| {
+            routes.respond(Method::GET, "/", res)
+        }),
+    };
+    routes.start().await
+}
+
+#[tokio::test]
+async fn bench_should_count_errors_and_variance_per_side() -> Result<()> {
+    let (server1, server2) = (serve(false).await?, serve(true).await?);
+    let (url1, url2) = (server1.url(), server2.url());
+    let config = DiffConfig::from_yaml(&format!(
+        r#"
+bench:
+  req1:
+    url: {url1}/
+  req2:
+    url: {url2}/
+  res:
+    skip_headers: [date]
+"#
+    ))?;
+    let cases = config.get_profile("bench").unwrap().cases("bench")?;
+    let bench = Bench {
+        case: &cases[0],
+        requests: 40,
+        concurrency: 8,
+        args: &DiffArgs::default(),
+    };
+    let report = bench.run().await?;
+
+    assert_eq!(report.req1.requests, 40);
+    assert_eq!(report.req1.errors, 0);
+    assert_eq!(report.req1.distinct, 1);
+    assert_eq!(report.req2.errors, 10);
+    assert_eq!(report.req2.statuses[&500], 10);
+    // Successful responses alternate between two bodies, one of them matching req1's.
+    assert_eq!(report.req2.distinct, 2);
+    assert_eq!(report.compared, 40);
+    assert_eq!(report.differing, 30);
+    assert!(report.req1.p50 <= report.req1.p99);
+    assert!(report.render()?.contains("10 (25.0%)"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn bench_latencies_should_leave_out_the_limiter_wait() -> Result<()> {
+    let server = serve(false).await?;
+    let url = server.url();
+    let config = DiffConfig::from_yaml(&format!(
+        r#"
+limits:
+  rps: 40
+bench:
+  req1:
+    url: {url}/
+  req2:
+    url: {url}/
+  res:
+    skip_headers: [date]
+"#
+    ))?;
+    let cases = config.get_profile("bench").unwrap().cases("bench")?;
+    let args = DiffArgs {
+        limiter: Limiter::new(&config.limits)?,
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let report = Bench {
+        case: &cases[0],
+        requests: 10,
+        concurrency: 8,
+        args: &args,
+    }
+    .run()
+    .await?;
+
+    // 20 requests paced 25ms apart, with most of them queued behind the pace: counting the
+    // wait, the median request would take about half of the whole run.
+    let elapsed = start.elapsed();
+    assert!(report.req1.p50 * 5 < elapsed, "{:?}", report.req1);
+    assert!(report.req2.p50 * 5 < elapsed, "{:?}", report.req2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn responses_that_fail_to_filter_should_count_as_differing() -> Result<()> {
+    let server = serve(false).await?;
+    let url = server.url();
+    let config = DiffConfig::from_yaml(&format!(
+        r#"
+bench:
+  req1:
+    url: {url}/
+  req2:
+    url: {url}/
+  res:
+    skip_headers: [date]
+    transform:
+      req2: 'error("no n")'
+"#
+    ))?;
+    let cases = config.get_profile("bench").unwrap().cases("bench")?;
+    let report = Bench {
+        case: &cases[0],
+        requests: 4,
+        concurrency: 2,
+        args: &DiffArgs::default(),
+    }
+    .run()
+    .await?;
+    assert_eq!((report.compared, report.differing), (4, 4));
+    assert_eq!((report.req1.errors, report.req2.errors), (0, 0));
+    Ok(())
+}
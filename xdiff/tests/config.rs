@@ -0,0 +1,89 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use tokio::process::Command;
+use xdiff::DiffConfig;
+
+#[tokio::test]
+async fn config_should_load_as_toml_and_json_by_extension() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let toml = dir.path().join("xdiff.toml");
+    std::fs::write(
+        &toml,
+        r#"
+[users.req1]
+url = "http://localhost:8080/v1/users"
+params = { page = 1 }
+
+[users.req2]
+url = "http://localhost:8080/v2/users"
+
+[users.res]
+skip_headers = ["date"]
+"#,
+    )?;
+    let config = DiffConfig::load(&toml).await?;
+    let profile = config.get_profile("users").unwrap();
+    assert_eq!(profile.req1.url.path(), "/v1/users");
+    assert_eq!(profile.req1.params, Some(json!({ "page": 1 })));
+    assert_eq!(profile.res.skip_headers, ["date"]);
+
+    let json = dir.path().join("xdiff.json");
+    std::fs::write(
+        &json,
+        r#"{ "users": { "req1": { "url": "http://localhost:8080/v1/users" },
+            "req2": { "url": "http://localhost:8080/v2/users" }, "res": {} } }"#,
+    )?;
+    let config = DiffConfig::load(&json).await?;
+    assert_eq!(
+        config.get_profile("users").unwrap().req2.url.path(),
+        "/v2/users"
+    );
+
+    // A TOML file holding JSON is reported as TOML that failed to parse.
+    std::fs::write(&toml, std::fs::read_to_string(&json)?)?;
+    let err = DiffConfig::load(&toml).await.unwrap_err();
+    assert!(format!("{:#}", err).starts_with(&format!("failed to parse {}", toml.display())));
+    Ok(())
+}
+
+#[tokio::test]
+async fn config_should_round_trip_through_every_format() -> Result<()> {
+    let config = DiffConfig::from_yaml(include_str!("../fixtures/test.yaml"))?;
+    let dir = tempfile::tempdir()?;
+    for name in ["xdiff.yml", "xdiff.toml", "xdiff.json"] {
+        let path = dir.path().join(name);
+        std::fs::write(&path, config.to_string_for(&path)?)?;
+        let loaded = DiffConfig::load(&path).await?;
+        assert_eq!(loaded.to_yaml()?, config.to_yaml()?, "{}", name);
+    }
+    assert!(config.to_string_for("xdiff.toml")?.contains("[rust.req1]"));
+    assert!(config.to_string_for("xdiff.json")?.starts_with("{\n"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn schema_should_describe_every_profile() -> Result<()> {
+    let output = Command::new(env!("CARGO_BIN_EXE_xdiff"))
+        .arg("schema")
+        .output()
+        .await?;
+    assert!(output.status.success());
+    let schema: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(schema["title"], "DiffConfig");
+    assert_eq!(
+        schema["additionalProperties"],
+        json!({ "$ref": "#/$defs/DiffProfile" })
+    );
+    assert_eq!(
+        schema["$defs"]["DiffProfile"]["required"],
+        json!(["req1", "req2", "res"])
+    );
+
+    // Every reference points at a definition of the schema.
+    let text = String::from_utf8(output.stdout)?;
+    for reference in text.split("\"#/$defs/").skip(1) {
+        let name = &reference[..reference.find('"').unwrap()];
+        assert!(schema["$defs"].get(name).is_some(), "{}", name);
+    }
+    Ok(())
+}
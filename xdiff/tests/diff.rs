@@ -0,0 +1,81 @@
+use anyhow::Result;
+use xdiff::{diff_text, DiffOptions, Granularity};
+
+fn diff(text1: &str, text2: &str, options: DiffOptions) -> Result<String> {
+    let diff = diff_text(text1, text2, &options)?;
+    Ok(console::strip_ansi_codes(&diff).into_owned())
+}
+
+#[test]
+fn word_and_char_granularity_should_mark_changed_text() -> Result<()> {
+    let word = DiffOptions {
+        granularity: Granularity::Word,
+        ..Default::default()
+    };
+    assert_eq!(
+        diff(
+            "id: 1\nname: Ada Byron\n",
+            "id: 1\nname: Ada Lovelace\n",
+            word
+        )?,
+        "1   1    | id: 1\n2   2    |~name: Ada [-Byron-]{+Lovelace+}\n"
+    );
+
+    let char = DiffOptions {
+        granularity: Granularity::Char,
+        ..Default::default()
+    };
+    assert_eq!(
+        diff("color\n", "colour\n", char)?,
+        "1   1    |~colo{+u+}r\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn context_should_set_the_unchanged_lines_shown() -> Result<()> {
+    let text1 = "a\nb\nc\nd\ne\nf\ng\n";
+    let text2 = "a\nB\nc\nd\ne\nF\ng\n";
+    let context = |context| DiffOptions {
+        context,
+        ..Default::default()
+    };
+    let lines = |s: String| s.lines().map(str::to_string).collect::<Vec<_>>();
+
+    let none = lines(diff(text1, text2, context(0))?);
+    assert_eq!(none.len(), 5);
+    assert!(none[2].starts_with("----"));
+
+    // One line of context joins neither hunk, `d` stays hidden.
+    let one = lines(diff(text1, text2, context(1))?);
+    assert_eq!(one.len(), 9);
+    assert!(!one.iter().any(|l| l.ends_with("| d")));
+
+    // With two, the hunks meet at `d`.
+    let two = lines(diff(text1, text2, context(2))?);
+    assert!(!two.iter().any(|l| l.starts_with("----")));
+    Ok(())
+}
+
+#[test]
+fn ignore_options_should_hide_only_what_they_ignore() -> Result<()> {
+    let options = DiffOptions {
+        ignore_whitespace: true,
+        ignore_case: true,
+        ..Default::default()
+    };
+    assert_eq!(diff("Name:  Ada\n", "name: ada\n", options.clone())?, "");
+    // Lines that still differ are shown as they are.
+    let shown = diff("Name:  Ada\nage: 36\n", "name: ada\nage: 37\n", options)?;
+    assert!(shown.contains("|-age: 36"));
+    assert!(shown.contains("|+age: 37"));
+    assert!(shown.contains("| Name:  Ada"));
+
+    let word = DiffOptions {
+        granularity: Granularity::Word,
+        ignore_case: true,
+        ..Default::default()
+    };
+    assert_eq!(diff("Hello World\n", "hello world\n", word)?, "");
+    Ok(())
+}
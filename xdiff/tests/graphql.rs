@@ -0,0 +1,148 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::{json, Value};
+use xdiff::{
+    mock::{self, MockRoutes},
+    DiffArgs, DiffConfig,
+};
+
+fn graphql_config(graphql: &str) -> Result<DiffConfig> {
+    DiffConfig::from_yaml(&format!(
+        r#"
+user:
+  req1:
+    method: GET
+    url: http://127.0.0.1:9/graphql
+    graphql:
+      {graphql}
+  req2:
+    url: http://127.0.0.1:9/graphql
+    graphql:
+      {graphql}
+  res:
+    skip_headers: [content-type]
+"#
+    ))
+}
+
+#[tokio::test]
+async fn graphql_requests_should_need_exactly_one_query() -> Result<()> {
+    let config = graphql_config("query: '{ user { name } }'")?;
+    // The method of a GraphQL request is always POST, whatever the profile says.
+    assert_eq!(
+        config.get_profile("user").unwrap().req1.method(),
+        Method::POST
+    );
+
+    for graphql in [
+        "variables: { id: 1 }",
+        "{ query: '{ user { name } }', query_file: user.graphql }",
+    ] {
+        let config = graphql_config(graphql)?;
+        let error = config
+            .get_profile("user")
+            .unwrap()
+            .req1
+            .send(&DiffArgs::default())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "graphql needs exactly one of query and query_file"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn graphql_responses_should_render_errors_apart_from_data() -> Result<()> {
+    let config = graphql_config("query: '{ user { name email } }'")?;
+    let profile = config.get_profile("user").unwrap();
+    let res1 = mock::json(json!({ "data": { "user": { "name": "Ada", "email": "ada@x.io" } } }));
+    let res2 = mock::json(json!({
+        "data": { "user": { "name": "Ada", "email": null } },
+        "errors": [{ "message": "email is private", "path": ["user", "email"] }],
+        "extensions": { "cost": 2 }
+    }));
+    let (text1, text2) = profile.filter_text(&res1, &res2)?;
+    assert_eq!(
+        text1,
+        "HTTP/1.1 200 OK\n\ndata:\n{\n  \"user\": {\n    \"email\": \"ada@x.io\",\n    \"name\": \"Ada\"\n  }\n}\n"
+    );
+    let errors = text2.find("errors:\n").unwrap();
+    let data = text2.find("data:\n").unwrap();
+    let extensions = text2.find("extensions:\n").unwrap();
+    assert!(errors < data && data < extensions);
+    assert!(text2.contains("\n\ndata:\n"));
+
+    // Without GraphQL the same body is rendered as a whole.
+    let mut plain = profile.clone();
+    plain.req1.graphql = None;
+    plain.req2.graphql = None;
+    let (_, text2) = plain.filter_text(&res1, &res2)?;
+    assert!(!text2.contains("errors:\n"));
+    assert!(text2.contains("\"errors\": ["));
+    Ok(())
+}
+
+#[tokio::test]
+async fn graphql_requests_should_post_the_envelope() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(
+            Method::POST,
+            "/graphql",
+            mock::json(json!({ "data": { "user": { "name": "Ada" } } })),
+        )
+        .start()
+        .await?;
+    let dir = tempfile::tempdir()?;
+    std::fs::write(
+        dir.path().join("user.graphql"),
+        "query User($id: ID!) { user(id: $id) { name } }",
+    )?;
+    // The query file is found next to the config, wherever xdiff runs from.
+    let path = dir.path().join("xdiff.yml");
+    std::fs::write(
+        &path,
+        format!(
+            r#"
+user:
+  req1:
+    method: GET
+    url: {url}/graphql
+    graphql:
+      query: "{{ user(id: 1) {{ name }} }}"
+  req2:
+    url: {url}/graphql
+    graphql:
+      query_file: user.graphql
+      variables:
+        id: 1
+      operation_name: User
+  res: {{}}
+"#,
+            url = server.url(),
+        ),
+    )?;
+    let config = DiffConfig::load(&path).await?;
+    let profile = config.get_profile("user").unwrap();
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    assert_eq!(res1.body, res2.body);
+
+    let mut received = server.received();
+    received.sort_by_key(|r| r.body.len());
+    assert!(received.iter().all(|r| r.method == Method::POST));
+    assert_eq!(received[0].headers["content-type"], "application/json");
+    let body: Value = serde_json::from_str(&received[0].body)?;
+    assert_eq!(body, json!({ "query": "{ user(id: 1) { name } }" }));
+    let body: Value = serde_json::from_str(&received[1].body)?;
+    assert_eq!(
+        body,
+        json!({
+            "query": "query User($id: ID!) { user(id: $id) { name } }",
+            "variables": { "id": 1 },
+            "operationName": "User"
+        })
+    );
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use anyhow::Result;
+use prost_reflect::{DescriptorPool, DynamicMessage, MethodDescriptor, Value};
+use tokio::net::TcpListener;
+use tokio_stream::{wrappers::TcpListenerStream, StreamExt};
+use tonic::{
+    body::Body,
+    codegen::{http, Body as HttpBody, BoxFuture, Service, StdError},
+    server::{Grpc, NamedService, UnaryService},
+    transport::Server,
+    Request, Response, Status,
+};
+use xdiff::{grpc::DynamicCodec, DiffArgs, DiffConfig};
+
+const PROTO: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/greeter.proto");
+
+/// A `greeter.Greeter` server built from the proto at runtime, like the client side.
+#[derive(Clone)]
+struct Greeter {
+    greeting: &'static str,
+    method: MethodDescriptor,
+}
+
+struct SayHello(Greeter);
+
+impl UnaryService<DynamicMessage> for SayHello {
+    type Response = DynamicMessage;
+    type Future = Ready<Result<Response<DynamicMessage>, Status>>;
+
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+        let name = request
+            .get_ref()
+            .get_field_by_name("name")
+            .and_then(|v| v.as_str().map(String::from))
+            .unwrap_or_default();
+        if name.is_empty() {
+            return ready(Err(Status::invalid_argument("name is required")));
+        }
+        let mut reply = DynamicMessage::new(self.0.method.output());
+        reply.set_field_by_name(
+            "message",
+            Value::String(format!("{}, {}!", self.0.greeting, name)),
+        );
+        ready(Ok(Response::new(reply)))
+    }
+}
+
+impl<B> Service<http::Request<B>> for Greeter
+where
+    B: HttpBody + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Infallible>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let service = SayHello(self.clone());
+        let mut grpc = Grpc::new(DynamicCodec::new(self.method.input()));
+        Box::pin(async move { Ok(grpc.unary(service, request).await) })
+    }
+}
+
+impl NamedService for Greeter {
+    const NAME: &'static str = "greeter.Greeter";
+}
+
+/// Serve the greeter, returning its url and a count of the connections it accepted.
+async fn serve(greeting: &'static str) -> Result<(String, Arc<AtomicUsize>)> {
+    let pool = DescriptorPool::from_file_descriptor_set(protox::compile(
+        [PROTO],
+        [concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")],
+    )?)?;
+    let method = pool
+        .get_service_by_name("greeter.Greeter")
+        .and_then(|s| s.methods().next())
+        .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = connections.clone();
+    let incoming = TcpListenerStream::new(listener).map(move |conn| {
+        accepted.fetch_add(1, Ordering::SeqCst);
+        conn
+    });
+    tokio::spawn(
+        Server::builder()
+            .add_service(Greeter { greeting, method })
+            .serve_with_incoming(incoming),
+    );
+    Ok((url, connections))
+}
+
+fn config(url1: &str, url2: &str, name2: &str) -> Result<DiffConfig> {
+    DiffConfig::from_yaml(&format!(
+        r#"
+greeter:
+  req1:
+    url: {url1}
+    grpc:
+      proto: {PROTO}
+      method: greeter.Greeter/SayHello
+      request: {{ name: xdiff }}
+  req2:
+    url: {url2}
+    grpc:
+      proto: {PROTO}
+      method: greeter.Greeter/SayHello
+      request: {{ name: "{name2}" }}
+  res:
+    skip_headers: [date]
+"#
+    ))
+}
+
+#[tokio::test]
+async fn grpc_responses_should_be_diffed_as_json() -> Result<()> {
+    let ((url1, _), (url2, _)) = (serve("Hello").await?, serve("Hi").await?);
+    let config = config(&url1, &url2, "xdiff")?;
+    let profile = config.get_profile("greeter").unwrap();
+
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    let diff = profile.diff_responses(&DiffArgs::default(), &res1, &res2)?;
+    let diff = console::strip_ansi_codes(&diff);
+    assert!(diff.contains(r#"-  "message": "Hello, xdiff!""#));
+    assert!(diff.contains(r#"+  "message": "Hi, xdiff!""#));
+    // Default values are kept, so a field missing on one side still shows up.
+    assert!(diff.contains(r#""count": 0"#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn grpc_error_status_should_be_diffed() -> Result<()> {
+    let (url, _) = serve("Hello").await?;
+    let config = config(&url, &url, "")?;
+    let profile = config.get_profile("greeter").unwrap();
+
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    let diff = profile.diff_responses(&DiffArgs::default(), &res1, &res2)?;
+    let diff = console::strip_ansi_codes(&diff);
+    assert!(diff.contains(r#"+  "code": "InvalidArgument","#));
+    assert!(diff.contains(r#"+  "message": "name is required""#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn grpc_profiles_should_set_up_once_from_the_config_directory() -> Result<()> {
+    let (url, connections) = serve("Hello").await?;
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("protos"))?;
+    let proto = dir.path().join("protos").join("greeter.proto");
+    std::fs::copy(PROTO, &proto)?;
+    let path = dir.path().join("xdiff.yml");
+    std::fs::write(
+        &path,
+        config(&url, &url, "xdiff")?
+            .to_yaml()?
+            .replace(PROTO, "protos/greeter.proto"),
+    )?;
+    let config = DiffConfig::load(&path).await?;
+    let profile = config.get_profile("greeter").unwrap();
+
+    for _ in 0..3 {
+        let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+        assert_eq!(res1.body, res2.body);
+    }
+    // The descriptors were compiled once, and each side kept its connection.
+    std::fs::remove_file(&proto)?;
+    profile.fetch(&DiffArgs::default()).await?;
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use anyhow::Result;
+use reqwest::{header, Method};
+use serde_json::json;
+use xdiff::{
+    mock::{self, MockRoutes, MockServer},
+    CaseDetail, DiffArgs, DiffConfig, Outcome, OutputFormat, Report, RequestSummary,
+};
+
+/// Two upstreams sending the same headers in a different order and spacing.
+async fn upstreams() -> Result<(MockServer, MockServer)> {
+    let old = mock::json(json!({ "id": 1 }))
+        .with_header(header::CACHE_CONTROL, "no-cache, max-age=0")
+        .with_header(header::VARY, "Accept")
+        .with_header(header::VARY, "Origin")
+        .with_header(header::ETAG, "W/\"abc\"")
+        .with_header(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"a, b;  c.txt\"",
+        );
+    let new = mock::json(json!({ "id": 1 }))
+        .with_header(header::CACHE_CONTROL, "max-age = 0,no-cache")
+        .with_header(header::VARY, "Origin")
+        .with_header(header::VARY, "Accept")
+        .with_header(header::ETAG, " W/\"abc\"")
+        .with_header(
+            header::CONTENT_DISPOSITION,
+            "attachment;filename = \"a, b;  c.txt\"",
+        );
+    Ok((
+        MockRoutes::new()
+            .respond(Method::GET, "/", old)
+            .start()
+            .await?,
+        MockRoutes::new()
+            .respond(Method::GET, "/", new)
+            .start()
+            .await?,
+    ))
+}
+
+fn yaml(old: &MockServer, new: &MockServer, rules: &str) -> String {
+    format!(
+        r#"
+headers:
+  req1:
+    url: {}/
+  req2:
+    url: {}/
+  res:
+    skip_headers: [date, content-length]
+{}
+"#,
+        old.url(),
+        new.url(),
+        rules
+    )
+}
+
+#[tokio::test]
+async fn header_rules_should_make_equivalent_headers_equal() -> Result<()> {
+    let (old, new) = upstreams().await?;
+    let config = DiffConfig::from_yaml(&yaml(&old, &new, ""))?;
+    let profile = config.get_profile("headers").unwrap();
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    let (text1, text2) = profile.filter_text(&res1, &res2)?;
+    assert!(text1.contains("etag: W/\"abc\""));
+    assert!(text2.contains("etag: W/\"abc\""));
+    // Separators and whitespace inside quotes are left alone.
+    assert!(text2.contains("content-disposition: attachment; filename=\"a, b;  c.txt\""));
+    assert!(text2.contains("cache-control: max-age=0, no-cache"));
+    assert!(text2.contains("vary: Origin\nvary: Accept"));
+    assert_ne!(text1, text2);
+
+    let rules = "    headers:\n      sets: [vary]\n      unordered: [Cache-Control]";
+    let config = DiffConfig::from_yaml(&yaml(&old, &new, rules))?;
+    let (text1, text2) = config
+        .get_profile("headers")
+        .unwrap()
+        .filter_text(&res1, &res2)?;
+    assert!(text1.contains("cache-control: max-age=0, no-cache"));
+    assert!(text1.contains("vary: Accept\nvary: Origin"));
+    assert_eq!(text1, text2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn required_headers_should_be_reported_as_violations() -> Result<()> {
+    let (old, new) = upstreams().await?;
+    let rules = r#"    headers:
+      require:
+        content-type: application/json
+        cache-control: no-store
+        x-request-id: ~"#;
+    let config = DiffConfig::from_yaml(&yaml(&old, &new, rules))?;
+    let profile = config.get_profile("headers").unwrap();
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    let violations = profile.res.violations(&res1, &res2).await?;
+    assert_eq!(
+        violations,
+        [
+            "req1: header cache-control is no-cache, max-age=0, expected no-store",
+            "req1: header x-request-id is missing",
+            "req2: header cache-control is max-age=0, no-cache, expected no-store",
+            "req2: header x-request-id is missing",
+        ]
+    );
+
+    let summary = RequestSummary {
+        method: "GET".into(),
+        url: old.url(),
+        status: 200,
+        version: "HTTP/1.1".into(),
+        truncated: None,
+    };
+    let mut report = Report::default();
+    report.push(
+        "headers",
+        Outcome::Equal,
+        Some(CaseDetail {
+            req1: summary.clone(),
+            req2: summary,
+            text1: String::new(),
+            text2: String::new(),
+            violations,
+            options: Default::default(),
+        }),
+    );
+    assert!(!report.is_success());
+    let junit = report.render(OutputFormat::Junit)?;
+    assert!(junit.contains(r#"failures="1""#));
+    assert!(junit.contains(r#"<failure message="requirements not met">req1: header"#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn header_values_should_be_compared_case_sensitively() -> Result<()> {
+    let config = DiffConfig::from_yaml(
+        r#"
+headers:
+  req1:
+    url: http://localhost/a
+  req2:
+    url: http://localhost/b
+  res:
+    headers:
+      sets: [vary]
+      require:
+        content-type: Application/JSON
+"#,
+    )?;
+    let profile = config.get_profile("headers").unwrap();
+    let res1 = mock::json(json!({}))
+        .with_header(header::ETAG, "\"abc\"")
+        .with_header(header::VARY, "Accept");
+    let res2 = mock::json(json!({}))
+        .with_header(header::ETAG, "\"ABC\"")
+        .with_header(header::VARY, "accept");
+    let (text1, text2) = profile.filter_text(&res1, &res2)?;
+    assert!(text1.contains("etag: \"abc\"\nvary: Accept"), "{}", text1);
+    assert!(text2.contains("etag: \"ABC\"\nvary: accept"), "{}", text2);
+    assert_eq!(
+        profile.res.violations(&res1, &res2).await?,
+        [
+            "req1: header content-type is application/json, expected Application/JSON",
+            "req2: header content-type is application/json, expected Application/JSON",
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use xdiff::{diff_text, CaseDetail, DiffOptions, Granularity, Outcome, Report, RequestSummary};
+
+/// A report of one case, with its outcome computed from the texts like `xdiff run` does.
+fn case_report(name: &str, text1: &str, text2: &str, options: DiffOptions) -> Report {
+    let summary = RequestSummary {
+        method: "GET".into(),
+        url: "http://localhost/users?a=1&b=2".into(),
+        status: 200,
+        version: "HTTP/1.1".into(),
+        truncated: None,
+    };
+    let outcome = Outcome::from_diff(diff_text(text1, text2, &options));
+    let mut report = Report::default();
+    report.push(
+        name,
+        outcome,
+        Some(CaseDetail {
+            req1: summary.clone(),
+            req2: summary,
+            text1: text1.into(),
+            text2: text2.into(),
+            violations: Vec::new(),
+            options,
+        }),
+    );
+    report
+}
+
+#[test]
+fn html_report_should_show_each_case_side_by_side() -> Result<()> {
+    let text1 = "a\nb\nc\nd\ne\nname: Ada\n";
+    let text2 = "a\nb\nc\nd\ne\nname: <Grace>\n";
+    let mut report = case_report("users<1>", text1, text2, DiffOptions::default());
+    report.push(
+        "down",
+        Outcome::Error {
+            error: "connection refused".into(),
+        },
+        None,
+    );
+    let html = report.render_html()?;
+
+    assert!(html.contains(
+        "<span>2 cases</span><span>0 equal</span><span>1 different</span><span>1 errors</span>"
+    ));
+    assert!(html.contains(r#"<h2>users&lt;1&gt; <span class="badge different">different</span>"#));
+    assert!(html.contains("<td>http://localhost/users?a=1&amp;b=2</td>"));
+    assert!(html.contains(r#"<td class="num">6</td><td class="del">name: Ada</td>"#));
+    assert!(html.contains(r#"<td class="num">6</td><td class="ins">name: &lt;Grace&gt;</td>"#));
+    // Only the 3 lines before the change are kept as context.
+    assert!(html.contains("<summary>2 unchanged lines</summary>"));
+    assert!(html.contains(r#"<pre class="error">connection refused</pre>"#));
+    Ok(())
+}
+
+#[test]
+fn html_diff_should_follow_the_diff_options() -> Result<()> {
+    let options = DiffOptions {
+        ignore_case: true,
+        ignore_whitespace: true,
+        ..Default::default()
+    };
+    let report = case_report("case", "Name:  Ada\n", "name: ada\n", options);
+    assert!(report.is_success());
+    let html = report.render_html()?;
+    assert!(!html.contains(r#"<td class="del">"#));
+
+    let options = DiffOptions {
+        granularity: Granularity::Word,
+        context: 1,
+        ..Default::default()
+    };
+    let text1 = "a\nb\nc\nd\nname: Ada\n";
+    let text2 = "a\nb\nc\nd\nname: Grace\n";
+    let html = case_report("case", text1, text2, options).render_html()?;
+    assert!(html.contains(r#"<td class="del">name: <mark>Ada</mark></td>"#));
+    assert!(html.contains(r#"<td class="ins">name: <mark>Grace</mark></td>"#));
+    // Only `d` is kept as context.
+    assert!(html.contains("<summary>3 unchanged lines</summary>"));
+    Ok(())
+}
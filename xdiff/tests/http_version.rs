@@ -0,0 +1,93 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::{json, Value};
+use tokio::process::Command;
+use xdiff::{
+    mock::{self, MockRoutes},
+    DiffArgs, DiffConfig,
+};
+
+fn yaml(url: &str, skip_version: bool) -> String {
+    format!(
+        r#"
+migration:
+  req1:
+    url: {url}/users/1
+    http_version: http1
+  req2:
+    url: {url}/users/1
+    http_version: http2-prior-knowledge
+  res:
+    skip_headers: [date]
+    skip_version: {skip_version}
+"#
+    )
+}
+
+#[tokio::test]
+async fn http_versions_should_be_compared_unless_skipped() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(Method::GET, "/users/1", mock::json(json!({ "id": 1 })))
+        .start()
+        .await?;
+
+    let config = DiffConfig::from_yaml(&yaml(&server.url(), false))?;
+    let diff = config
+        .get_profile("migration")
+        .unwrap()
+        .diff(DiffArgs::default())
+        .await?;
+    let diff = console::strip_ansi_codes(&diff);
+    assert!(diff.contains("-HTTP/1.1 200 OK"));
+    assert!(diff.contains("+HTTP/2.0 200 OK"));
+
+    let config = DiffConfig::from_yaml(&yaml(&server.url(), true))?;
+    let profile = config.get_profile("migration").unwrap();
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    let (text1, text2) = profile.filter_text(&res1, &res2)?;
+    assert!(text1.starts_with("200 OK"));
+    assert_eq!(text1, text2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn negotiated_versions_should_be_noted_in_the_report() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(Method::GET, "/users/1", mock::json(json!({ "id": 1 })))
+        .start()
+        .await?;
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("xdiff.yml");
+    std::fs::write(&path, yaml(&server.url(), true))?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_xdiff"))
+        .args(["run", "-c"])
+        .arg(&path)
+        .args(["-f", "json"])
+        .output()
+        .await?;
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(report["cases"][0]["status"], "equal");
+    assert_eq!(
+        report["cases"][0]["notes"][0],
+        "protocol versions differ: req1 HTTP/1.1, req2 HTTP/2.0"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn http2_should_fail_when_the_server_answers_with_another_version() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(Method::GET, "/users/1", mock::json(json!({ "id": 1 })))
+        .start()
+        .await?;
+    let config = DiffConfig::from_yaml(
+        &yaml(&server.url(), false)
+            .replace("http_version: http2-prior-knowledge", "http_version: http2"),
+    )?;
+    let profile = config.get_profile("migration").unwrap();
+    let error = profile.fetch(&DiffArgs::default()).await.unwrap_err();
+    assert!(format!("{:#}", error).contains("got HTTP/1.1"));
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use reqwest::{Method, Url};
+use serde_json::{json, Value};
+use tokio::process::Command;
+use xdiff::{
+    mock::{self, MockRoutes},
+    DiffArgs, DiffConfig, LimitConfig, Limiter,
+};
+
+#[tokio::test]
+async fn limiter_should_pace_requests_and_cap_each_host() -> Result<()> {
+    let limiter = Limiter::new(&LimitConfig {
+        rps: Some(20.0),
+        ..Default::default()
+    })?;
+    let url = Url::parse("http://localhost:8080/")?;
+    let start = Instant::now();
+    for _ in 0..5 {
+        limiter.acquire(&url).await;
+    }
+    assert!(start.elapsed() >= Duration::from_millis(200));
+
+    let limiter = Limiter::new(&LimitConfig {
+        per_host: Some(2),
+        ..Default::default()
+    })?;
+    let (current, max) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+    let tasks: Vec<_> = (0..6)
+        .map(|_| {
+            let (limiter, url) = (limiter.clone(), url.clone());
+            let (current, max) = (current.clone(), max.clone());
+            tokio::spawn(async move {
+                let _permit = limiter.acquire(&url).await;
+                let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                max.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                current.fetch_sub(1, Ordering::SeqCst);
+            })
+        })
+        .collect();
+    // Another host has a cap of its own.
+    let other = limiter
+        .acquire(&Url::parse("http://localhost:9090/")?)
+        .await;
+    for task in tasks {
+        task.await?;
+    }
+    drop(other);
+    assert_eq!(max.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn run_should_work_on_cases_at_once_and_keep_their_order() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(Method::GET, "/users/1", mock::json(json!({ "id": 1 })))
+        .respond(Method::GET, "/users/2", mock::json(json!({ "id": 2 })))
+        .start()
+        .await?;
+    let url = server.url();
+    let config = format!(
+        r#"
+limits:
+  rps: 50
+  per_host: 2
+  concurrency: 3
+a:
+  req1:
+    url: {url}/users/1
+  req2:
+    url: {url}/users/1
+  res:
+    skip_headers: [date]
+b:
+  req1:
+    url: {url}/users/1
+  req2:
+    url: {url}/users/2
+  res:
+    skip_headers: [date]
+c:
+  req1:
+    url: {url}/users/2
+  req2:
+    url: {url}/users/2
+  res:
+    skip_headers: [date]
+"#
+    );
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("xdiff.yml");
+    std::fs::write(&path, config)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_xdiff"))
+        .args(["run", "-c"])
+        .arg(&path)
+        .args(["-f", "json"])
+        .output()
+        .await?;
+    let report: Value = serde_json::from_slice(&output.stdout)?;
+    let cases: Vec<_> = report["cases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| (c["name"].as_str().unwrap(), c["status"].as_str().unwrap()))
+        .collect();
+    assert_eq!(cases, [("a", "equal"), ("b", "different"), ("c", "equal")]);
+    assert_eq!(server.received().len(), 6);
+
+    std::fs::write(&path, "limits:\n  rps: 0\n  concurrency: 0\n")?;
+    let output = Command::new(env!("CARGO_BIN_EXE_xdiff"))
+        .args(["validate", "-c"])
+        .arg(&path)
+        .output()
+        .await?;
+    assert!(!output.status.success());
+    let problems = String::from_utf8(output.stdout)? + String::from_utf8(output.stderr)?.as_str();
+    assert!(problems.contains("rps must be a positive number, got 0"));
+    assert!(problems.contains("must be at least 1"));
+
+    std::fs::write(&path, "limits:\n  rps: -1\n")?;
+    let output = Command::new(env!("CARGO_BIN_EXE_xdiff"))
+        .args(["run", "-c"])
+        .arg(&path)
+        .output()
+        .await?;
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)?.contains("rps must be a positive number, got -1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn streamed_sides_on_one_host_should_share_its_only_slot() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(Method::GET, "/users/1", mock::json(json!({ "id": 1 })))
+        .start()
+        .await?;
+    let url = server.url();
+    let config = DiffConfig::from_yaml(&format!(
+        r#"
+limits:
+  per_host: 1
+users:
+  req1:
+    url: {url}/users/1
+  req2:
+    url: {url}/users/1
+  res:
+    stream: true
+    skip_headers: [date]
+"#
+    ))?;
+    let args = DiffArgs {
+        limiter: Limiter::new(&config.limits)?,
+        ..Default::default()
+    };
+    let diff = config.get_profile("users").unwrap().diff(args);
+    let diff = tokio::time::timeout(Duration::from_secs(5), diff).await??;
+    assert_eq!(diff, "");
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use anyhow::Result;
+use xdiff::DiffConfig;
+
+fn yaml(matrix: &str) -> String {
+    format!(
+        r#"
+users:
+  matrix:
+    {matrix}
+  req1:
+    url: http://localhost/v1/users/{{{{id}}}}?locale={{{{locale}}}}
+  req2:
+    url: http://localhost/v2/users/{{{{id}}}}?locale={{{{locale}}}}
+  res: {{}}
+"#
+    )
+}
+
+#[test]
+fn matrix_should_expand_into_one_case_per_combination() -> Result<()> {
+    let config = DiffConfig::from_yaml(&yaml(
+        "id: { start: 1, end: 3, step: 2 }\n    locale: [en, fr]",
+    ))?;
+    let cases = config.get_profile("users").unwrap().cases("users")?;
+    let names: Vec<_> = cases.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "users[id=1,locale=en]",
+            "users[id=1,locale=fr]",
+            "users[id=3,locale=en]",
+            "users[id=3,locale=fr]",
+        ]
+    );
+    assert_eq!(
+        cases[1].profile.req2.url.as_str(),
+        "http://localhost/v2/users/1?locale=fr"
+    );
+    assert!(cases.iter().all(|c| c.profile.matrix.is_none()));
+    Ok(())
+}
+
+#[test]
+fn matrix_csv_rows_should_combine_with_variables() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let csv = dir.path().join("ids.csv");
+    std::fs::write(&csv, "id,tenant\n7,acme\n8,globex\n")?;
+    let config =
+        DiffConfig::from_yaml(&yaml(&format!("csv: {}\n    locale: [en]", csv.display())))?;
+    let cases = config.get_profile("users").unwrap().cases("users")?;
+    let names: Vec<_> = cases.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "users[id=7,locale=en,tenant=acme]",
+            "users[id=8,locale=en,tenant=globex]",
+        ]
+    );
+    assert_eq!(
+        cases[0].profile.req1.url.as_str(),
+        "http://localhost/v1/users/7?locale=en"
+    );
+
+    let config = DiffConfig::from_yaml(&yaml("id: { start: 1, end: 2, step: 0 }"))?;
+    let error = config.get_profile("users").unwrap().cases("users");
+    assert_eq!(
+        error.unwrap_err().to_string(),
+        "matrix range step must be positive, got 0"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn matrix_csv_should_be_relative_to_the_config_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir_all(dir.path().join("data"))?;
+    std::fs::write(dir.path().join("data/ids.csv"), "id\n1\n2\n")?;
+    let path = dir.path().join("xdiff.yml");
+    std::fs::write(&path, yaml("csv: data/ids.csv"))?;
+
+    let config = DiffConfig::load(&path).await?;
+    let cases = config.get_profile("users").unwrap().cases("users")?;
+    let names: Vec<_> = cases.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["users[id=1]", "users[id=2]"]);
+    // The saved config keeps the path as written.
+    assert!(config.to_yaml()?.contains("csv: data/ids.csv"));
+
+    std::fs::write(dir.path().join("data/ids.csv"), "id\n")?;
+    let config = DiffConfig::load(&path).await?;
+    let error = config.get_profile("users").unwrap().cases("users");
+    assert_eq!(
+        error.unwrap_err().to_string(),
+        "matrix csv data/ids.csv has no rows"
+    );
+    Ok(())
+}
+
+#[test]
+fn empty_matrices_should_be_errors() -> Result<()> {
+    for matrix in ["id: []", "id: { start: 3, end: 1 }"] {
+        let config = DiffConfig::from_yaml(&yaml(matrix))?;
+        let error = config.get_profile("users").unwrap().cases("users");
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "matrix variable `id` has no values"
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use std::path::Path;
+
+use anyhow::Result;
+use reqwest::{header, Method, StatusCode};
+use serde_json::{json, Value};
+use tokio::process::Command;
+use xdiff::{
+    mock::{self, MockRoutes, MockServer, Side},
+    DiffArgs, DiffConfig,
+};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+
+/// Two upstreams for `GET /users/1` that differ in a skipped field, a header and the name.
+async fn upstreams() -> Result<(MockServer, MockServer)> {
+    let user = |name: &str, request_id: &str| {
+        mock::json(json!({ "id": 1, "name": name, "request_id": request_id }))
+            .with_header(header::CACHE_CONTROL, "no-cache")
+    };
+    let old = MockRoutes::new()
+        .respond(Method::GET, "/users/1", user("Ada", "a1"))
+        .start()
+        .await?;
+    let new = MockRoutes::new()
+        .respond(
+            Method::GET,
+            "/users/1",
+            user("Ada Lovelace", "b2").with_header(header::VARY, "accept"),
+        )
+        .start()
+        .await?;
+    Ok((old, new))
+}
+
+fn config(old: &MockServer, new: &MockServer) -> String {
+    format!(
+        r#"
+users:
+  req1:
+    url: {}/users/1
+  req2:
+    url: {}/users/1
+  res:
+    skip_headers: [date, content-length]
+    skip_body: [request_id]
+"#,
+        old.url(),
+        new.url()
+    )
+}
+
+#[tokio::test]
+async fn mock_upstreams_should_be_diffed_with_skip_rules() -> Result<()> {
+    let (old, new) = upstreams().await?;
+    let config = DiffConfig::from_yaml(&config(&old, &new))?;
+    let profile = config.get_profile("users").unwrap();
+
+    let diff = profile.diff(DiffArgs::default()).await?;
+    let diff = console::strip_ansi_codes(&diff);
+    assert!(diff.contains(r#"-  "name": "Ada""#));
+    assert!(diff.contains(r#"+  "name": "Ada Lovelace""#));
+    assert!(diff.contains("+vary: accept"));
+    assert!(!diff.contains("request_id"));
+    assert_eq!(old.received().len(), 1);
+    assert_eq!(old.received()[0].uri, "/users/1");
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_routes_should_answer_in_sequence() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(Method::GET, "/flaky", mock::json(json!({ "ok": true })))
+        .respond(
+            Method::GET,
+            "/flaky",
+            mock::text(StatusCode::SERVICE_UNAVAILABLE, "down"),
+        )
+        .respond(Method::GET, "/flaky?v=2", mock::json(json!({ "v": 2 })))
+        .start()
+        .await?;
+    let get = |path: &str| reqwest::get(format!("{}{}", server.url(), path));
+
+    assert_eq!(get("/flaky").await?.status(), 200);
+    assert_eq!(get("/flaky").await?.status(), 503);
+    assert_eq!(get("/flaky?v=1").await?.status(), 200);
+    assert_eq!(get("/flaky?v=2").await?.text().await?, r#"{"v":2}"#);
+    assert_eq!(get("/missing").await?.status(), 404);
+    Ok(())
+}
+
+#[tokio::test]
+async fn run_should_render_every_output_format() -> Result<()> {
+    let (old, new) = upstreams().await?;
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("xdiff.yml");
+    std::fs::write(&path, config(&old, &new))?;
+    let run = |format: &'static str| {
+        let path = path.clone();
+        async move {
+            let output = Command::new(env!("CARGO_BIN_EXE_xdiff"))
+                .args(["run", "-c"])
+                .arg(&path)
+                .args(["-f", format])
+                .output()
+                .await?;
+            // The case differs, which fails the run.
+            assert_eq!(output.status.code(), Some(1));
+            anyhow::Ok(String::from_utf8(output.stdout)?)
+        }
+    };
+
+    let report: Value = serde_json::from_str(&run("json").await?)?;
+    assert_eq!(report["cases"][0]["status"], "different");
+    assert!(report["cases"][0]["diff"]
+        .as_str()
+        .unwrap()
+        .contains("Ada Lovelace"));
+    let junit = run("junit").await?;
+    assert!(junit.contains(r#"<testsuite name="xdiff" tests="1" failures="1" errors="0">"#));
+    assert!(run("terminal").await?.contains("Ada Lovelace"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn snapshots_should_be_served_as_an_upstream() -> Result<()> {
+    let config = DiffConfig::from_yaml(include_str!("../fixtures/test.yaml"))?;
+    let snapshots = Path::new(FIXTURES).join("snapshots");
+    let server = MockRoutes::from_snapshots(&config, &snapshots, Side::Req2)
+        .await?
+        .start()
+        .await?;
+
+    let res = reqwest::get(format!("{}/todos/2?c=200", server.url())).await?;
+    assert_eq!(res.headers()["x-amz-cf-id"], "Cd34");
+    let body: Value = serde_json::from_str(&res.text().await?)?;
+    assert_eq!(body["id"], 2);
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use anyhow::Result;
+use reqwest::header::HeaderName;
+use serde_json::{json, Value};
+use tokio::process::Command;
+use xdiff::{mock, Outcome, RedactConfig, Redactor};
+
+fn redactor() -> Result<Redactor> {
+    Redactor::new(&RedactConfig {
+        headers: vec!["X-Tenant-Secret".into()],
+        patterns: vec![r"tok_[a-z0-9]+".into(), r"(?i)password=\w+".into()],
+    })
+}
+
+#[test]
+fn secrets_should_be_masked_in_headers_and_bodies() -> Result<()> {
+    let mut res = mock::json(json!({
+        "user": "ada",
+        "Authorization": "Bearer abc",
+        "nested": [{ "x-api-key": "k1" }],
+        "note": "uses tok_123abc"
+    }))
+    .with_header(reqwest::header::AUTHORIZATION, "Bearer abc")
+    .with_header(reqwest::header::SET_COOKIE, "session=s3cr3t")
+    .with_header(HeaderName::from_static("x-tenant-secret"), "tenant-1")
+    .with_header(HeaderName::from_static("x-trace"), "id tok_9f and more")
+    .with_header(HeaderName::from_static("x-other"), "plain");
+    redactor()?.redact_response(&mut res);
+
+    let header = |name: &str| res.headers[name].to_str().unwrap().to_string();
+    assert_eq!(header("authorization"), "[REDACTED]");
+    assert_eq!(header("set-cookie"), "[REDACTED]");
+    assert_eq!(header("x-tenant-secret"), "[REDACTED]");
+    assert_eq!(header("x-trace"), "id [REDACTED] and more");
+    assert_eq!(header("x-other"), "plain");
+    let body: Value = serde_json::from_str(&res.body)?;
+    assert_eq!(
+        body,
+        json!({
+            "user": "ada",
+            "Authorization": "[REDACTED]",
+            "nested": [{ "x-api-key": "[REDACTED]" }],
+            "note": "uses [REDACTED]"
+        })
+    );
+
+    let mut text = mock::text(reqwest::StatusCode::OK, "login Password=hunter2 ok");
+    redactor()?.redact_response(&mut text);
+    assert_eq!(text.body, "login [REDACTED] ok");
+    Ok(())
+}
+
+#[test]
+fn secrets_should_be_masked_in_urls_and_errors() -> Result<()> {
+    let redactor = redactor()?;
+    assert_eq!(
+        redactor.redact_text("https://api.example.com/users?token=tok_abc&page=2"),
+        "https://api.example.com/users?token=[REDACTED]&page=2"
+    );
+    let outcome = redactor.redact_outcome(Outcome::Error {
+        error: "request to https://api.example.com/?key=tok_abc failed".into(),
+    });
+    assert_eq!(
+        outcome,
+        Outcome::Error {
+            error: "request to https://api.example.com/?key=[REDACTED] failed".into()
+        }
+    );
+    // Diffs are built from redacted responses, so they are left as they are.
+    let diff = Outcome::Different {
+        diff: "tok_abc".into(),
+    };
+    assert_eq!(redactor.redact_outcome(diff.clone()), diff);
+    Ok(())
+}
+
+#[tokio::test]
+async fn run_reports_should_not_leak_secrets_from_failed_requests() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("xdiff.yml");
+    // Nothing listens on port 9, so both requests fail with their URL in the error.
+    std::fs::write(
+        &path,
+        "redact:\n  patterns: ['tok_[a-z0-9]+']\nusers:\n  req1:\n    url: http://127.0.0.1:9/users?token=tok_abc\n  req2:\n    url: http://127.0.0.1:9/users?token=tok_abc\n  res: {}\n",
+    )?;
+    let output = Command::new(env!("CARGO_BIN_EXE_xdiff"))
+        .args(["run", "-p", "users", "-c"])
+        .arg(&path)
+        .args(["-f", "json"])
+        .output()
+        .await?;
+    let report = String::from_utf8(output.stdout)?;
+    assert!(report.contains("\"status\": \"error\""));
+    assert!(report.contains("[REDACTED]"));
+    assert!(!report.contains("tok_abc"));
+    Ok(())
+}
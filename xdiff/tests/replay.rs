@@ -0,0 +1,108 @@
+use std::path::Path;
+
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::{json, Value};
+use tokio::process::Command;
+use xdiff::{
+    mock::{self, MockRoutes},
+    DiffArgs, DiffConfig, Snapshot,
+};
+
+const SNAPSHOTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/snapshots");
+
+#[tokio::test]
+async fn replayed_snapshot_should_apply_skip_rules() -> Result<()> {
+    let config = DiffConfig::from_yaml(include_str!("../fixtures/test.yaml"))?;
+    let profile = config.get_profile("todo").unwrap();
+    let snapshot = Snapshot::load(SNAPSHOTS, "todo").await?;
+
+    let diff = profile.diff_responses(&DiffArgs::default(), &snapshot.req1, &snapshot.req2)?;
+    let diff = console::strip_ansi_codes(&diff);
+    assert!(!diff.contains("report-to"));
+    assert!(diff.contains("x-amz-cf-id: Cd34"));
+    assert!(diff.contains(r#""id": 2"#));
+    Ok(())
+}
+
+/// Run `xdiff run` with `mode`, `--record` or `--replay`, and a JSON report. Returns whether
+/// it succeeded, the report and what it printed to stderr.
+async fn run(config: &Path, mode: &str, snapshots: &Path) -> Result<(bool, Value, String)> {
+    let output = Command::new(env!("CARGO_BIN_EXE_xdiff"))
+        .args(["run", "-f", "json", "-c"])
+        .arg(config)
+        .arg(mode)
+        .arg(snapshots)
+        .output()
+        .await?;
+    let stderr = String::from_utf8(output.stderr)?;
+    let report = serde_json::from_slice(&output.stdout).unwrap_or(Value::Null);
+    Ok((output.status.success(), report, stderr))
+}
+
+#[tokio::test]
+async fn recorded_responses_should_replay_without_the_servers() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(Method::GET, "/v1/todo", mock::json(json!({ "id": 1 })))
+        .respond(Method::GET, "/v2/todo", mock::json(json!({ "id": 2 })))
+        .start()
+        .await?;
+    let dir = tempfile::tempdir()?;
+    let config = dir.path().join("xdiff.yml");
+    std::fs::write(
+        &config,
+        format!(
+            "todo:\n  req1:\n    url: {url}/v1/todo\n  req2:\n    url: {url}/v2/todo\n  res:\n    skip_headers: [date]\n",
+            url = server.url()
+        ),
+    )?;
+    let snapshots = dir.path().join("snapshots");
+
+    let (_, recorded, _) = run(&config, "--record", &snapshots).await?;
+    assert_eq!(recorded["cases"][0]["status"], "different");
+    let snapshot = Snapshot::load(&snapshots, "todo").await?;
+    assert_eq!(snapshot.req2.body, r#"{"id":2}"#);
+
+    drop(server);
+    let (_, replayed, stderr) = run(&config, "--replay", &snapshots).await?;
+    assert_eq!(replayed, recorded, "{}", stderr);
+    Ok(())
+}
+
+#[tokio::test]
+async fn cases_sharing_a_snapshot_file_should_be_rejected() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let config = dir.path().join("xdiff.yml");
+    let profile = "  req1:\n    url: http://127.0.0.1:9/a\n  req2:\n    url: http://127.0.0.1:9/b\n  res: {}\n";
+    std::fs::write(
+        &config,
+        format!("todo list:\n{profile}todo/list:\n{profile}"),
+    )?;
+    let snapshots = dir.path().join("snapshots");
+
+    let (success, _, stderr) = run(&config, "--record", &snapshots).await?;
+    assert!(!success);
+    assert!(
+        stderr
+            .contains("cases `todo list` and `todo/list` would share the snapshot todo_list.json"),
+        "{}",
+        stderr
+    );
+    assert!(!snapshots.exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn broken_snapshots_should_name_their_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("todo.json"), "{ \"req1\": ")?;
+    let error = Snapshot::load(dir.path(), "todo").await.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "failed to parse snapshot {}",
+            dir.path().join("todo.json").display()
+        )
+    );
+    Ok(())
+}
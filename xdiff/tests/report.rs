@@ -0,0 +1,71 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use xdiff::{Outcome, OutputFormat, Report};
+
+/// One case of each outcome, with names and messages that need escaping, and a colored diff.
+fn report() -> Report {
+    let mut report = Report::default();
+    report.push("equal", Outcome::Equal, None);
+    report.push(
+        "users<&>",
+        Outcome::Different {
+            diff: format!(
+                "{}\n",
+                console::style("-\"name\": \"Ada\"")
+                    .red()
+                    .force_styling(true)
+            ),
+        },
+        None,
+    );
+    report.push(
+        "down",
+        Outcome::Error {
+            error: "connection refused: \"127.0.0.1\"".into(),
+        },
+        None,
+    );
+    report.cases[1].notes.push("req1 body truncated".into());
+    report
+}
+
+#[test]
+fn json_reports_should_hold_plain_outcomes() -> Result<()> {
+    let output: Value = serde_json::from_str(&report().render(OutputFormat::Json)?)?;
+    assert_eq!(
+        output,
+        json!({
+            "cases": [
+                { "name": "equal", "status": "equal" },
+                {
+                    "name": "users<&>",
+                    "status": "different",
+                    "diff": "-\"name\": \"Ada\"\n",
+                    "notes": ["req1 body truncated"]
+                },
+                { "name": "down", "status": "error", "error": "connection refused: \"127.0.0.1\"" }
+            ]
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn junit_reports_should_count_and_escape_cases() -> Result<()> {
+    let junit = report().render(OutputFormat::Junit)?;
+    let lines: Vec<_> = junit.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<testsuite name="xdiff" tests="3" failures="1" errors="1">"#,
+            r#"  <testcase classname="xdiff" name="equal"/>"#,
+            r#"  <testcase classname="xdiff" name="users&lt;&amp;&gt;"><failure message="responses differ">-&quot;name&quot;: &quot;Ada&quot;"#,
+            r#"</failure><system-out>req1 body truncated</system-out></testcase>"#,
+            r#"  <testcase classname="xdiff" name="down"><error message="connection refused: &quot;127.0.0.1&quot;"/></testcase>"#,
+            "</testsuite>",
+        ]
+    );
+    assert!(!report().is_success());
+    Ok(())
+}
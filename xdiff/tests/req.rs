@@ -0,0 +1,59 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::{json, Value};
+use tokio::process::Command;
+use xdiff::mock::{self, MockRoutes};
+
+#[tokio::test]
+async fn req_should_send_one_side_and_print_the_response() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(
+            Method::GET,
+            "/v1/users/1",
+            mock::json(json!({ "name": "Ada" })),
+        )
+        .respond(
+            Method::GET,
+            "/v2/users/1",
+            mock::json(json!({ "name": "Ada", "tags": ["a", "b"] })),
+        )
+        .start()
+        .await?;
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("xdiff.yml");
+    std::fs::write(
+        &path,
+        format!(
+            "users:\n  req1:\n    url: {url}/v1/users/1\n  req2:\n    url: {url}/v2/users/1\n  res: {{}}\n",
+            url = server.url()
+        ),
+    )?;
+    let req = |args: &'static [&'static str]| {
+        let path = path.clone();
+        async move {
+            let output = Command::new(env!("CARGO_BIN_EXE_xdiff"))
+                .args(["req", "-p", "users", "-c"])
+                .arg(&path)
+                .args(args)
+                .output()
+                .await?;
+            assert!(output.status.success());
+            anyhow::Ok(String::from_utf8(output.stdout)?)
+        }
+    };
+
+    let text = req(&[]).await?;
+    assert!(text.starts_with("HTTP/1.1 200 OK\n"));
+    assert!(text.contains("content-type: application/json\n"));
+    assert!(text.ends_with("{\n  \"name\": \"Ada\"\n}\n"));
+
+    let responses: Value =
+        serde_json::from_str(&req(&["-s", "req2", "--json", "-e", "%x-token=abc"]).await?)?;
+    assert_eq!(responses[0]["name"], "users");
+    assert_eq!(responses[0]["status"], 200);
+    assert_eq!(responses[0]["body"]["tags"], json!(["a", "b"]));
+    let received = server.received();
+    assert_eq!(received[1].uri, "/v2/users/1");
+    assert_eq!(received[1].headers["x-token"], "abc");
+    Ok(())
+}
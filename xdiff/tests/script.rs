@@ -0,0 +1,63 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::json;
+use xdiff::{
+    mock::{self, MockRoutes, MockServer},
+    DiffArgs, DiffConfig, Script,
+};
+
+/// A server answering both versions with the same body.
+async fn serve() -> Result<MockServer> {
+    let res = || mock::json(json!({ "ok": true, "served_at": 1 }));
+    MockRoutes::new()
+        .respond(Method::GET, "/v1", res())
+        .respond(Method::POST, "/v2", res())
+        .start()
+        .await
+}
+
+#[tokio::test]
+async fn scripts_should_rewrite_requests_and_responses() -> Result<()> {
+    let server = serve().await?;
+    let url = server.url();
+    let config = DiffConfig::from_yaml(&format!(
+        r#"
+signed:
+  req1:
+    url: {url}/v1
+    script:
+      pre: |
+        request.url += "?signed=1";
+        request.headers["x-signature"] = hmac_sha256_hex("secret", request.url);
+      post: |
+        response.body.remove("served_at");
+  req2:
+    method: POST
+    url: {url}/v2
+  res:
+    skip_headers: [date]
+"#
+    ))?;
+    let profile = config.get_profile("signed").unwrap();
+
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    let diff = profile.diff_responses(&DiffArgs::default(), &res1, &res2)?;
+    let diff = console::strip_ansi_codes(&diff);
+    assert!(diff.contains(r#"+  "served_at": 1"#));
+    let received = server.received();
+    let signed = received.iter().find(|r| r.method == Method::GET).unwrap();
+    assert_eq!(signed.uri, "/v1?signed=1");
+    assert_eq!(signed.headers["x-signature"].len(), 64);
+    Ok(())
+}
+
+#[test]
+fn scripts_should_be_sandboxed() -> Result<()> {
+    assert!(Script::new(r#"import "std" as std;"#)?
+        .run("x", json!(1))
+        .is_err());
+    assert!(Script::new(r#"eval("x = 2")"#).is_err());
+    assert!(Script::new("loop {}")?.run("x", json!(1)).is_err());
+    assert_eq!(Script::new("x += 1;")?.run("x", json!(1))?, json!(2));
+    Ok(())
+}
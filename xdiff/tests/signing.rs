@@ -0,0 +1,116 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use reqwest::{Client, Method};
+use serde_json::json;
+use tokio::process::Command;
+use xdiff::{
+    mock::{self, MockRoutes},
+    signing::SigningProfile,
+    DiffConfig,
+};
+
+/// Looks credentials up in `vars` rather than in the process environment, which other tests
+/// share.
+fn lookup(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+    move |name| {
+        vars.iter()
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| v.to_string())
+    }
+}
+
+#[test]
+fn aws_sigv4_should_match_the_reference_signature() -> Result<()> {
+    // The get-vanilla case of the AWS SigV4 test suite.
+    let env = lookup(&[
+        ("XDIFF_TEST_AWS_ACCESS", "AKIDEXAMPLE"),
+        (
+            "XDIFF_TEST_AWS_SECRET",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+        ),
+    ]);
+    let signing: SigningProfile = serde_json::from_value(json!({
+        "aws_sigv4": {
+            "region": "us-east-1",
+            "service": "service",
+            "access_key_env": "XDIFF_TEST_AWS_ACCESS",
+            "secret_key_env": "XDIFF_TEST_AWS_SECRET",
+            "session_token_env": "XDIFF_TEST_AWS_TOKEN",
+        }
+    }))?;
+    let mut req = Client::new()
+        .get("https://example.amazonaws.com/")
+        .build()?;
+    let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1440938160);
+    signing.sign_with(&mut req, now, &env)?;
+
+    assert_eq!(req.headers()["x-amz-date"], "20150830T123600Z");
+    assert_eq!(
+        req.headers()["authorization"],
+        "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+         SignedHeaders=host;x-amz-date, \
+         Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn hmac_signature_should_cover_command_line_overrides() -> Result<()> {
+    let server = MockRoutes::new()
+        .respond(Method::POST, "/items", mock::json(json!({})))
+        .respond(Method::GET, "/items", mock::json(json!({})))
+        .start()
+        .await?;
+    let url = server.url();
+    let yaml = format!(
+        r#"
+signed:
+  req1:
+    method: POST
+    url: {url}/items?b=2&a=1
+    body: {{ name: x }}
+    signing:
+      hmac:
+        key_env: XDIFF_TEST_HMAC_KEY
+        headers: [x-tenant]
+  req2:
+    url: {url}/items
+  res: {{}}
+"#
+    );
+    let config = DiffConfig::from_yaml(&yaml)?;
+    let profile = config.get_profile("signed").unwrap();
+    let signing = profile.req1.signing.clone().unwrap();
+    let env = lookup(&[("XDIFF_TEST_HMAC_KEY", "secret")]);
+    let sign = |body: &str, tenant: &str| -> Result<String> {
+        let mut req = Client::new()
+            .post("http://localhost/items?b=2&a=1")
+            .header("x-tenant", tenant)
+            .body(body.to_string())
+            .build()?;
+        signing.sign_with(&mut req, SystemTime::now(), &env)?;
+        Ok(req.headers()["x-signature"].to_str()?.to_string())
+    };
+    let signature = sign(r#"{"name":"y"}"#, "a")?;
+    assert_eq!(signature.len(), 64);
+    assert_ne!(signature, sign(r#"{"name":"x"}"#, "a")?);
+    assert_ne!(signature, sign(r#"{"name":"y"}"#, "b")?);
+
+    // The key only reaches the binary through its own environment.
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("xdiff.yml");
+    std::fs::write(&path, &yaml)?;
+    let output = Command::new(env!("CARGO_BIN_EXE_xdiff"))
+        .args(["req", "-p", "signed", "-c"])
+        .arg(&path)
+        .args(["-e", "%x-tenant=a", "-e", "@name=\"y\""])
+        .env("XDIFF_TEST_HMAC_KEY", "secret")
+        .output()
+        .await?;
+    assert!(output.status.success());
+    let received = server.received();
+    let signed = received.iter().find(|r| r.method == Method::POST).unwrap();
+    assert_eq!(signed.headers["x-signature"], signature.as_str());
+    Ok(())
+}
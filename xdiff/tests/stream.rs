@@ -0,0 +1,163 @@
+use anyhow::Result;
+use reqwest::{header, Method, StatusCode};
+use xdiff::{
+    mock::{self, MockRoutes, MockServer},
+    DiffArgs, DiffConfig,
+};
+
+/// A server answering `/export` with `lines` numbered lines, where the `changed` lines say
+/// `diff` instead of `same`.
+async fn serve(lines: usize, changed: &[usize]) -> Result<MockServer> {
+    let body: String = (0..lines)
+        .map(|i| {
+            let word = if changed.contains(&i) { "diff" } else { "same" };
+            format!("{:08} {}\n", i, word)
+        })
+        .collect();
+    let res = mock::text(StatusCode::OK, body).with_header(header::CONTENT_TYPE, "text/plain");
+    MockRoutes::new()
+        .respond(Method::GET, "/export", res)
+        .start()
+        .await
+}
+
+fn config(url1: &str, url2: &str, res: &str) -> Result<DiffConfig> {
+    DiffConfig::from_yaml(&format!(
+        r#"
+export:
+  req1:
+    url: {url1}/export
+  req2:
+    url: {url2}/export
+  res:
+    skip_headers: [date]
+    {res}
+"#
+    ))
+}
+
+#[tokio::test]
+async fn streamed_bodies_should_keep_only_windows_around_differences() -> Result<()> {
+    // 100k lines of 14 bytes, about 1.4 MB, differing once in the middle.
+    let (server1, server2) = (serve(100_000, &[60_000]).await?, serve(100_000, &[]).await?);
+    let config = config(&server1.url(), &server2.url(), "stream: true")?;
+    let profile = config.get_profile("export").unwrap();
+
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    // Skipped ranges are marked in the body, which isn't cut by a limit.
+    let truncated = res1.truncated.unwrap();
+    assert_eq!((truncated.read, truncated.limited), (1_400_000, false));
+    assert!(truncated.kept < 200_000);
+    assert!(res1.body.len() < 200_000);
+    assert!(res1.body.contains("[... skipped to byte"));
+    let diff = profile.diff_responses(&DiffArgs::default(), &res1, &res2)?;
+    let diff = console::strip_ansi_codes(&diff);
+    assert!(diff.contains("-00060000 diff"));
+    assert!(diff.contains("+00060000 same"));
+    assert!(!diff.contains("00000000 same"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn bodies_should_be_cut_at_max_body_size() -> Result<()> {
+    let (server1, server2) = (serve(1000, &[500]).await?, serve(1000, &[]).await?);
+    let config = config(&server1.url(), &server2.url(), "max_body_size: 140")?;
+    let profile = config.get_profile("export").unwrap();
+
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    assert_eq!(res1.body.len(), 140);
+    let truncated = res2.truncated.unwrap();
+    assert!(truncated.read > 140);
+    assert_eq!((truncated.kept, truncated.limited), (140, true));
+    // The difference is past the limit.
+    let diff = profile.diff_responses(&DiffArgs::default(), &res1, &res2)?;
+    assert!(diff.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn bodies_under_max_body_size_should_not_be_truncated() -> Result<()> {
+    let (server1, server2) = (serve(1000, &[]).await?, serve(1000, &[]).await?);
+    let limited = config(&server1.url(), &server2.url(), "max_body_size: 100000")?;
+    let profile = limited.get_profile("export").unwrap();
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    assert_eq!((res1.truncated, res2.truncated), (None, None));
+
+    // Equal streamed bodies keep nothing, without being cut by the limit.
+    let streamed = config(
+        &server1.url(),
+        &server2.url(),
+        "stream: true\n    max_body_size: 100000",
+    )?;
+    let profile = streamed.get_profile("export").unwrap();
+    let (res1, _) = profile.fetch(&DiffArgs::default()).await?;
+    let truncated = res1.truncated.unwrap();
+    assert_eq!((truncated.kept, truncated.limited), (0, false));
+    assert!(res1.body.is_empty());
+
+    let config = config(
+        &server1.url(),
+        &server2.url(),
+        "stream: true\n    max_body_size: 1400",
+    )?;
+    let (res1, _) = config
+        .get_profile("export")
+        .unwrap()
+        .fetch(&DiffArgs::default())
+        .await?;
+    let truncated = res1.truncated.unwrap();
+    assert!(truncated.read >= 1400);
+    assert!(truncated.limited);
+    Ok(())
+}
+
+#[tokio::test]
+async fn regions_past_the_limit_should_be_counted() -> Result<()> {
+    // Ten differences, far enough apart to be regions of their own.
+    let changed: Vec<usize> = (0..10).map(|i| i * 10_000 + 5_000).collect();
+    let (server1, server2) = (serve(100_000, &changed).await?, serve(100_000, &[]).await?);
+    let config = config(&server1.url(), &server2.url(), "stream: true")?;
+    let profile = config.get_profile("export").unwrap();
+
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    assert_eq!(res1.truncated.unwrap().hidden_regions, 2);
+    assert!(res1
+        .truncated
+        .unwrap()
+        .to_string()
+        .ends_with(", 2 more differing regions not shown"));
+    let diff = profile.diff_responses(&DiffArgs::default(), &res1, &res2)?;
+    let diff = console::strip_ansi_codes(&diff);
+    assert!(diff.contains("[... 2 more differing regions not shown ...]"));
+    assert!(diff.contains("-00075000 diff"));
+    assert!(!diff.contains("00085000 diff"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn streamed_responses_should_skip_body_assertions() -> Result<()> {
+    let (server1, server2) = (serve(1000, &[]).await?, serve(1000, &[]).await?);
+    let body = config(
+        &server1.url(),
+        &server2.url(),
+        "stream: true\n    assert: [\"status == 200\", \"$.items.length > 0\"]",
+    )?;
+    let profile = body.get_profile("export").unwrap();
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    assert!(profile.res.violations(&res1, &res2).await?.is_empty());
+
+    let config = config(
+        &server1.url(),
+        &server2.url(),
+        "stream: true\n    assert: [\"status == 201\"]",
+    )?;
+    let profile = config.get_profile("export").unwrap();
+    assert_eq!(
+        profile.res.violations(&res1, &res2).await?,
+        [
+            "req1: status == 201: got 200",
+            "req2: status == 201: got 200"
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use serde_json::json;
+use xdiff::{mock, DiffConfig, Transform};
+
+fn yaml(transform: &str) -> String {
+    format!(
+        r#"
+users:
+  req1:
+    url: http://localhost/v1/users
+  req2:
+    url: http://localhost/v2/users
+  res:
+    skip_headers: [date]
+    transform:
+      {transform}
+"#
+    )
+}
+
+#[test]
+fn transforms_should_reshape_each_side_before_the_diff() -> Result<()> {
+    let res1 = mock::json(json!({ "data": { "users": [{ "id": 1, "name": "Ada" }] } }));
+    let res2 = mock::json(json!({ "items": [{ "name": "Ada", "id": 1 }], "next": null }));
+    let config = DiffConfig::from_yaml(&yaml(
+        "req1: .data.users\n      req2: '.items | map({id, name})'",
+    ))?;
+    let profile = config.get_profile("users").unwrap();
+    let (text1, text2) = profile.filter_text(&res1, &res2)?;
+    assert_eq!(text1, text2);
+    assert!(text1.contains("\"name\": \"Ada\""));
+
+    // Only req2 is transformed, so the envelopes differ.
+    let config = DiffConfig::from_yaml(&yaml("req2: .items"))?;
+    let profile = config.get_profile("users").unwrap();
+    let (text1, text2) = profile.filter_text(&res1, &res2)?;
+    assert!(text1.contains("\"data\""));
+    assert!(!text2.contains("\"next\""));
+    Ok(())
+}
+
+#[test]
+fn transforms_should_fail_on_errors_and_missing_output() -> Result<()> {
+    let res = mock::json(json!({ "name": "Ada", "tags": ["a", "b"] }));
+    let config = DiffConfig::from_yaml(&yaml("req1: .name + 1"))?;
+    let error = config
+        .get_profile("users")
+        .unwrap()
+        .filter_text(&res, &res)
+        .unwrap_err();
+    assert!(format!("{:#}", error).contains("transform `.name + 1` failed"));
+
+    let none = Transform::new("empty")?.apply(json!({}));
+    assert_eq!(
+        none.unwrap_err().to_string(),
+        "transform `empty` produced no output"
+    );
+    let many = Transform::new(".tags[]")?.apply(json!({ "tags": ["a", "b"] }))?;
+    assert_eq!(many, json!(["a", "b"]));
+    let invalid = Transform::new(".tags[").err().unwrap();
+    assert!(invalid
+        .to_string()
+        .starts_with("failed to parse transform `.tags[`"));
+    Ok(())
+}
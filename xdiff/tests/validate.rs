@@ -0,0 +1,127 @@
+use std::path::Path;
+
+use xdiff::Validation;
+
+/// Every problem found in `content`, read as the format of `name`, with its position.
+fn located(name: &str, content: &str) -> Vec<String> {
+    Validation::new(Path::new(name), content)
+        .into_problems()
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn problems(content: &str) -> Vec<String> {
+    Validation::new(Path::new("xdiff.yml"), content)
+        .into_problems()
+        .iter()
+        .map(|p| format!("{}: {}", p.path, p.message))
+        .collect()
+}
+
+#[test]
+fn extension_methods_should_be_valid() {
+    let config = |method: &str| {
+        format!(
+            "purge:\n  req1:\n    method: {method}\n    url: http://localhost/a\n  req2:\n    method: {method}\n    url: http://localhost/b\n  res: {{}}\n"
+        )
+    };
+    assert!(problems(&config("PURGE")).is_empty());
+    assert!(problems(&config("PROPFIND")).is_empty());
+    assert_eq!(
+        problems(&config("get"))[0],
+        "purge.req1.method: unknown method `get`, methods are case-sensitive: use `GET`"
+    );
+    assert_eq!(
+        problems(&config("\"BAD METHOD\""))[0],
+        "purge.req1.method: invalid method `BAD METHOD`"
+    );
+}
+
+#[test]
+fn body_assertions_should_be_reported_in_stream_mode() {
+    let config = "export:\n  req1:\n    url: http://localhost/a\n  req2:\n    url: http://localhost/b\n  res:\n    stream: true\n    assert:\n      - status == 200\n      - $.items.length > 0\n";
+    assert_eq!(
+        problems(config),
+        ["export.res.assert[1]: body assertions are skipped when `stream` is set"]
+    );
+}
+
+#[test]
+fn yaml_problems_should_all_be_reported_at_their_line_and_column() {
+    let config = r#"todo:
+  req1:
+    method: "GET X"
+    url: not a url
+    headers:
+      "bad header": x
+    colour: red
+  req2:
+    url: http://localhost/b
+  res:
+    skip_body:
+      - $.items[
+    unordered_arrays:
+      - $..[
+    assert:
+      - $.x ==
+"#;
+    assert_eq!(
+        located("xdiff.yml", config),
+        [
+            "3:5: todo.req1.method: invalid method `GET X`",
+            "4:5: todo.req1.url: invalid url `not a url`: relative URL without a base",
+            "6:7: todo.req1.headers.bad header: invalid header name `bad header`",
+            "7:5: todo.req1.colour: unknown key `colour`",
+            "12:9: todo.res.skip_body[0]: JSONPath `$.items[` has an unclosed `[`",
+            "14:9: todo.res.unordered_arrays[0]: JSONPath `$..[` has an unclosed `[`",
+            "16:9: todo.res.assert[0]: assertion `$.x ==` has no value to compare with",
+        ]
+    );
+}
+
+#[test]
+fn toml_problems_should_all_be_reported_at_their_line_and_column() {
+    let config = r#"[todo.req1]
+method = "GET X"
+url = "not a url"
+colour = "red"
+
+[todo.req1.headers]
+"bad header" = "x"
+
+[todo.req2]
+url = "http://localhost/b"
+
+[todo.res]
+skip_body = ["$.items["]
+"#;
+    assert_eq!(
+        located("xdiff.toml", config),
+        [
+            "2:1: todo.req1.method: invalid method `GET X`",
+            "3:1: todo.req1.url: invalid url `not a url`: relative URL without a base",
+            "4:1: todo.req1.colour: unknown key `colour`",
+            "7:1: todo.req1.headers.bad header: invalid header name `bad header`",
+            "13:14: todo.res.skip_body[0]: JSONPath `$.items[` has an unclosed `[`",
+        ]
+    );
+}
+
+#[test]
+fn missing_sections_should_be_reported_at_their_profile() {
+    let config = "todo:\n  req1:\n    url: http://localhost/a\n  res: {}\n";
+    assert_eq!(located("xdiff.yml", config), ["1:1: todo: missing `req2`"]);
+}
+
+#[test]
+fn syntax_errors_should_be_the_only_problem() {
+    assert_eq!(
+        located("xdiff.yml", "todo:\n  req1: [\n"),
+        ["3:1: did not find expected node content at line 3 column 1, while parsing a flow node"]
+    );
+    assert_eq!(
+        located("xdiff.toml", "[todo.req1\nurl = 1\n"),
+        ["1:11: unclosed table, expected `]`"]
+    );
+}
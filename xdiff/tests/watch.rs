@@ -0,0 +1,93 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::{json, Value};
+use xdiff::{
+    mock::{self, MockRoutes},
+    DiffArgs, DiffConfig, Redactor, Watcher,
+};
+
+#[tokio::test]
+async fn watch_should_report_transitions_and_log_every_poll() -> Result<()> {
+    let server1 = MockRoutes::new()
+        .respond(Method::GET, "/todo", mock::json(json!({ "id": 1 })))
+        .start()
+        .await?;
+    // Equal twice, then different, then equal again as the sequence starts over.
+    let server2 = MockRoutes::new()
+        .respond(Method::GET, "/todo", mock::json(json!({ "id": 1 })))
+        .respond(Method::GET, "/todo", mock::json(json!({ "id": 1 })))
+        .respond(Method::GET, "/todo", mock::json(json!({ "id": 2 })))
+        .start()
+        .await?;
+    let config = DiffConfig::from_yaml(&format!(
+        "todo:\n  req1:\n    url: {}/todo\n  req2:\n    url: {}/todo\n  res:\n    skip_headers: [date]\n",
+        server1.url(),
+        server2.url()
+    ))?;
+    let cases = config.get_profile("todo").unwrap().cases("todo")?;
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("watch.jsonl");
+    let watcher = Watcher {
+        cases: &cases,
+        interval: Duration::from_secs(1),
+        log: Some(log.clone()),
+        redactor: &Redactor::default(),
+        args: &DiffArgs::default(),
+    };
+
+    let mut last = HashMap::new();
+    let mut out = Vec::new();
+    let mut reported = Vec::new();
+    for _ in 0..4 {
+        watcher.poll(&mut last, &mut out).await?;
+        let text = String::from_utf8(std::mem::take(&mut out))?;
+        reported.push(
+            text.lines()
+                .next()
+                .map(|l| l.split_once("] ").unwrap().1.to_string()),
+        );
+    }
+    assert_eq!(
+        reported,
+        [
+            Some("todo: responses are equal".to_string()),
+            None,
+            Some("todo: responses differ".to_string()),
+            Some("todo: responses are equal".to_string()),
+        ]
+    );
+
+    let records: Vec<Value> = std::fs::read_to_string(&log)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    let summary: Vec<_> = records
+        .iter()
+        .map(|r| {
+            (
+                r["profile"].clone(),
+                r["changed"].clone(),
+                r["status"].clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (json!("todo"), json!(true), json!("equal")),
+            (json!("todo"), json!(false), json!("equal")),
+            (json!("todo"), json!(true), json!("different")),
+            (json!("todo"), json!(true), json!("equal")),
+        ]
+    );
+    let diff = records[2]["diff"].as_str().unwrap();
+    assert!(
+        diff.contains("-  \"id\": 1") && diff.contains("+  \"id\": 2"),
+        "{}",
+        diff
+    );
+    assert!(records.iter().all(|r| r["timestamp"].is_string()));
+    Ok(())
+}
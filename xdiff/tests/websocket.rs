@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+use xdiff::{DiffArgs, DiffConfig};
+
+/// A server answering every text frame with a JSON frame echoing it, from a server named `name`.
+async fn serve(name: &'static str) -> Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("ws://{}/ws", listener.local_addr()?);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut socket = accept_async(stream).await?;
+                let mut seq = 0;
+                while let Some(Ok(Message::Text(text))) = socket.next().await {
+                    seq += 1;
+                    let reply =
+                        serde_json::json!({ "server": name, "seq": seq, "echo": text.as_str() });
+                    socket.send(Message::text(reply.to_string())).await?;
+                }
+                anyhow::Ok(())
+            });
+        }
+    });
+    Ok(url)
+}
+
+#[tokio::test]
+async fn websocket_transcripts_should_be_diffed_with_skip_rules() -> Result<()> {
+    let (url1, url2) = (serve("a").await?, serve("b").await?);
+    let config = DiffConfig::from_yaml(&format!(
+        r#"
+chat:
+  req1:
+    url: {url1}
+    websocket:
+      send: [hello, {{ join: room }}]
+      timeout: 200ms
+  req2:
+    url: {url2}
+    websocket:
+      send: [hello, {{ join: lobby }}]
+      expect: 2
+  res:
+    skip_headers: [date, sec-websocket-accept]
+    skip_body: ['$[*].server']
+"#
+    ))?;
+    let profile = config.get_profile("chat").unwrap();
+
+    let (res1, res2) = profile.fetch(&DiffArgs::default()).await?;
+    assert_eq!(res1.status.as_u16(), 101);
+    let diff = profile.diff_responses(&DiffArgs::default(), &res1, &res2)?;
+    let diff = console::strip_ansi_codes(&diff);
+    assert!(!diff.contains("server"));
+    assert!(diff.contains(r#"-    "echo": "{\"join\":\"room\"}","#));
+    assert!(diff.contains(r#"+    "echo": "{\"join\":\"lobby\"}","#));
+    assert!(diff.contains(r#""seq": 2"#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn endless_frames_should_stop_at_the_deadline() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("ws://{}/ws", listener.local_addr()?);
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await?;
+        let mut socket = accept_async(stream).await?;
+        while socket.send(Message::text("tick")).await.is_ok() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        anyhow::Ok(())
+    });
+    let config = DiffConfig::from_yaml(&format!(
+        r#"
+ticks:
+  req1:
+    url: {url}
+    websocket:
+      deadline: 300ms
+  req2:
+    url: {url}
+  res: {{}}
+"#
+    ))?;
+    let req = &config.get_profile("ticks").unwrap().req1;
+
+    let start = Instant::now();
+    let res =
+        tokio::time::timeout(Duration::from_secs(5), req.send(&DiffArgs::default())).await??;
+    assert!(start.elapsed() < Duration::from_secs(1));
+    let frames: Vec<String> = serde_json::from_str(&res.body)?;
+    assert!(!frames.is_empty());
+    Ok(())
+}